@@ -1,4 +1,9 @@
 fn main() {
+    // Headless tools are built without the raylib feature so they don't need the library
+    if std::env::var_os("CARGO_FEATURE_RAYLIB").is_none() {
+        return;
+    }
+
     println!("cargo:rerun-if-changed=raylib/*"); // Re-run build script if anything in the clib directory changes
     println!("cargo:rustc-link-lib=raylib"); // Link against the 'myclib' library
     println!("cargo:rustc-link-search=raylib"); // Search for library in the 'clib' directory
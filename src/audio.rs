@@ -0,0 +1,229 @@
+//! ## Beeper
+//! CHIP-8 plays a single tone while the sound timer is not 0. The [`Beeper`]
+//! generates the samples of this tone. It is pulled by the audio backend each
+//! time it needs more samples, so the sound only depends on the audio clock
+//! and not on how fast instructions are emulated.
+//!
+//! The tone fades in and out over a few milliseconds to avoid clicks when
+//! the sound timer starts or stops.
+//!
+//! ## Recording
+//! A [`Recorder`] renders the tone of a session in a 16-bit PCM WAV file.
+//! It is driven by the emulated time instead of the audio device, so it also
+//! works on machines without sound.
+
+use std::f32::consts::PI;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Default sample rate in Hz
+pub const SAMPLE_RATE: u32 = 44100;
+/// Default frequency of the tone in Hz
+pub const DEFAULT_FREQUENCY: f32 = 440.0;
+/// Default volume, from 0 to 1
+pub const DEFAULT_VOLUME: f32 = 0.25;
+/// Duration of the fade in/out in seconds
+const FADE_DURATION: f32 = 0.005;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Triangle,
+    Sawtooth,
+    Sine,
+}
+
+impl Waveform {
+    /// Return the value of the wave at `phase` (from 0 to 1)
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+        }
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            "sawtooth" => Ok(Waveform::Sawtooth),
+            "sine" => Ok(Waveform::Sine),
+            _ => Err(format!(
+                "unknown waveform '{s}' (square, triangle, sawtooth or sine)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Waveform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Waveform::Square => "square",
+            Waveform::Triangle => "triangle",
+            Waveform::Sawtooth => "sawtooth",
+            Waveform::Sine => "sine",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone)]
+pub struct Beeper {
+    sample_rate: u32,
+    pub frequency: f32,
+    pub volume: f32,
+    pub waveform: Waveform,
+    pub muted: bool,
+    /// Position in the current period, from 0 to 1
+    phase: f32,
+    /// Current gain, it moves toward 1 when the tone is played and toward 0
+    /// otherwise.
+    gain: f32,
+}
+
+impl Beeper {
+    pub fn new(sample_rate: u32) -> Self {
+        Beeper {
+            sample_rate,
+            frequency: DEFAULT_FREQUENCY,
+            volume: DEFAULT_VOLUME,
+            waveform: Waveform::default(),
+            muted: false,
+            phase: 0.0,
+            gain: 0.0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Fill `samples` with the tone if `playing` is true, with silence
+    /// otherwise.
+    pub fn fill(&mut self, samples: &mut [i16], playing: bool) {
+        let target = if playing && !self.muted { 1.0 } else { 0.0 };
+        let fade_step = 1.0 / (FADE_DURATION * self.sample_rate as f32);
+        let phase_step = self.frequency / self.sample_rate as f32;
+
+        for s in samples.iter_mut() {
+            if self.gain < target {
+                self.gain = (self.gain + fade_step).min(target);
+            } else if self.gain > target {
+                self.gain = (self.gain - fade_step).max(target);
+            }
+
+            let value = self.waveform.sample(self.phase) * self.gain * self.volume.clamp(0.0, 1.0);
+            *s = (value * i16::MAX as f32) as i16;
+
+            // The phase keeps going even when it is silent so the wave is
+            // continuous when the tone starts again.
+            self.phase = (self.phase + phase_step).fract();
+        }
+    }
+}
+
+/// Write mono 16-bit PCM samples in a WAV file. Sizes in the header are
+/// written by [`WavWriter::finish`].
+pub struct WavWriter<W: Write + Seek> {
+    out: W,
+    /// Number of samples written so far
+    samples: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut out: W, sample_rate: u32) -> io::Result<Self> {
+        let block_align: u16 = 2; // one channel of 16 bits
+        out.write_all(b"RIFF")?;
+        out.write_all(&0_u32.to_le_bytes())?; // patched by finish()
+        out.write_all(b"WAVE")?;
+        out.write_all(b"fmt ")?;
+        out.write_all(&16_u32.to_le_bytes())?;
+        out.write_all(&1_u16.to_le_bytes())?; // PCM
+        out.write_all(&1_u16.to_le_bytes())?; // channels
+        out.write_all(&sample_rate.to_le_bytes())?;
+        out.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        out.write_all(&block_align.to_le_bytes())?;
+        out.write_all(&16_u16.to_le_bytes())?; // bits per sample
+        out.write_all(b"data")?;
+        out.write_all(&0_u32.to_le_bytes())?; // patched by finish()
+        Ok(WavWriter { out, samples: 0 })
+    }
+
+    pub fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        for s in samples {
+            self.out.write_all(&s.to_le_bytes())?;
+        }
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    /// Write the sizes in the header and return the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let data_size = self.samples * 2;
+        self.out.seek(SeekFrom::Start(4))?;
+        self.out.write_all(&(36 + data_size).to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(40))?;
+        self.out.write_all(&data_size.to_le_bytes())?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Render the tone of a session in a WAV file
+pub struct Recorder {
+    beeper: Beeper,
+    wav: WavWriter<BufWriter<File>>,
+    /// Fraction of sample not rendered yet, so durations that are not a
+    /// multiple of the sample period don't drift.
+    pending: f64,
+    buffer: Vec<i16>,
+}
+
+impl Recorder {
+    /// Create `path`. The tone uses the settings of `beeper`, except that it
+    /// is never muted.
+    pub fn create(path: &Path, beeper: &Beeper) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut beeper = beeper.clone();
+        beeper.muted = false;
+
+        Ok(Recorder {
+            wav: WavWriter::new(file, beeper.sample_rate)?,
+            beeper,
+            pending: 0.0,
+            buffer: vec![],
+        })
+    }
+
+    /// Render `seconds` of sound, the tone is played if `playing` is true
+    pub fn advance(&mut self, seconds: f64, playing: bool) -> io::Result<()> {
+        self.pending += seconds * self.beeper.sample_rate as f64;
+        let count = self.pending as usize;
+        self.pending -= count as f64;
+
+        self.buffer.resize(count, 0);
+        self.beeper.fill(&mut self.buffer, playing);
+        self.wav.write(&self.buffer)
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        self.wav.finish().map(|_| ())
+    }
+}
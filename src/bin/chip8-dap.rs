@@ -0,0 +1,14 @@
+use chip8_emulator::dap::DapServer;
+use std::io;
+use std::process::exit;
+
+fn main() {
+    // Logs go to stderr, stdout is used by the protocol
+    env_logger::init();
+
+    let server = DapServer::new(io::stdout());
+    if let Err(e) = server.run(io::stdin()) {
+        log::error!("{e}");
+        exit(1);
+    }
+}
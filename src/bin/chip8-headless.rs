@@ -0,0 +1,223 @@
+use chip8_emulator::audio::{Beeper, Recorder, SAMPLE_RATE};
+use chip8_emulator::config::Config;
+use chip8_emulator::crash;
+use chip8_emulator::database::Database;
+use chip8_emulator::emulator::Chip8;
+use chip8_emulator::headless::Headless;
+use chip8_emulator::image;
+use chip8_emulator::keyscript::KeyScript;
+use chip8_emulator::timestamp;
+use chip8_emulator::trace::Tracer;
+use chip8_emulator::video::{VideoRecorder, DEFAULT_SCALE};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+const USAGE: &str = "usage: chip8-headless <ROM> [OPTIONS]
+
+Run a ROM without window and dump the screen and the state of the machine.
+
+Options:
+    --frames <N>     run N frames (default: 60)
+    --cycles <N>     run N instructions instead of frames
+    --ipf <N>        instructions per frame (default: 6, or the one of the database)
+    --keys <FILE>    key script: '<frame> <key> <down|up>' or '<frame> reset|hard-reset'
+                     per line
+    --seed <N>       seed of the random generator, for reproducible runs
+    --screen <FILE>  write the screen as PNG, PBM or ASCII art (.png, .pbm, .txt)
+    --screenshot <DIR>
+                     write the screen in a PNG named after the current time in DIR
+    --state <FILE>   write the state of the machine as JSON
+    --trace <FILE>   write a trace of executed instructions (.jsonl or .csv)
+    --wav <FILE>     render the sound in a WAV file (16-bit PCM, 44100 Hz)
+    --video <FILE>   record the screen as an animated GIF or a Y4M stream (.gif, .y4m)
+    --scale <N>      scale of PNG screens (default: 1) and of the video (default: 4)
+    --crash-dir <DIR>
+                     write a crash bundle in DIR when the ROM fails or the emulator panics
+    --database <FILE>
+                     settings of known ROMs by SHA-1 (default: chip8-roms/database.json)
+
+Known ROMs run with the quirks and the instructions per frame of the database.
+Without --screen, --screenshot and --state the screen is printed on stdout.";
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{msg}");
+    exit(2);
+}
+
+fn parse<T: std::str::FromStr>(opt: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(&format!("{opt}: invalid value '{value}'")))
+}
+
+/// Write the screen in a format that depends on the extension of `path`
+fn write_screen(chip: &Chip8, path: &Path, scale: usize) -> std::io::Result<()> {
+    let fb = chip.get_framebuffer();
+    let (width, height) = chip.resolution();
+
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("png") => {
+            let rgb = image::to_rgb(fb, width, height, scale, [255; 3], [0; 3]);
+            fs::write(path, image::to_png(&rgb, width * scale, height * scale))
+        }
+        Some("pbm") => fs::write(path, image::to_pbm(fb, width, height)),
+        _ => fs::write(path, image::to_ascii(fb, width, height).join("\n") + "\n"),
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let a: Vec<String> = env::args().collect();
+    if a.len() < 2 || a[1] == "-h" || a[1] == "--help" {
+        println!("{USAGE}");
+        exit(if a.len() < 2 { 2 } else { 0 });
+    }
+
+    let rom = &a[1];
+    let mut frames: u64 = 60;
+    let mut cycles: Option<u64> = None;
+    let mut ipf = None;
+    let mut script = KeyScript::default();
+    let mut seed = None;
+    let mut screen = None;
+    let mut screenshot = None;
+    let mut state = None;
+    let mut tracer = None;
+    let mut recorder = None;
+    let mut video = None;
+    let mut scale = None;
+    let mut crash_dir = None;
+    let mut database_file = None;
+
+    let mut opts = a[2..].iter();
+    while let Some(opt) = opts.next() {
+        let value = opts
+            .next()
+            .unwrap_or_else(|| fail(&format!("{opt} expects a value")));
+
+        match opt.as_str() {
+            "--frames" => frames = parse(opt, value),
+            "--cycles" => cycles = Some(parse(opt, value)),
+            "--ipf" => ipf = Some(parse(opt, value)),
+            "--seed" => seed = Some(parse(opt, value)),
+            "--keys" => {
+                script = KeyScript::load(Path::new(value)).unwrap_or_else(|e| fail(&e.to_string()))
+            }
+            "--screen" => screen = Some(value.clone()),
+            "--screenshot" => screenshot = Some(value.clone()),
+            "--state" => state = Some(value.clone()),
+            "--trace" => {
+                tracer = Some(
+                    Tracer::create(Path::new(value))
+                        .unwrap_or_else(|e| fail(&format!("Failed to create {value}: {e}"))),
+                )
+            }
+            "--wav" => {
+                recorder = Some(
+                    Recorder::create(Path::new(value), &Beeper::new(SAMPLE_RATE))
+                        .unwrap_or_else(|e| fail(&format!("Failed to create {value}: {e}"))),
+                )
+            }
+            "--video" => video = Some(value.clone()),
+            "--scale" => scale = Some(parse::<usize>(opt, value).max(1)),
+            "--crash-dir" => crash_dir = Some(value.clone()),
+            "--database" => database_file = Some(value.clone()),
+            _ => fail(&format!("Unknown argument {opt}\n\n{USAGE}")),
+        }
+    }
+
+    let bytes = fs::read(rom).unwrap_or_else(|e| fail(&format!("Cannot read {rom}: {e}")));
+    let database =
+        Database::open(database_file.as_deref().map(Path::new)).unwrap_or_else(|e| fail(&e));
+
+    // The options win over the settings of the database
+    let mut config = Config {
+        seed,
+        ..Config::default()
+    };
+    if let Some(info) = database.get(&bytes) {
+        log::info!("{}", info.title);
+        info.apply(&mut config);
+    }
+    config.ipf = ipf.unwrap_or(config.ipf);
+
+    let mut chip = Chip8::default();
+    if let Err(e) = chip.load_bytes(&bytes) {
+        fail(&format!("Failed to load {rom}: {e}"));
+    }
+    chip.set_quirks(config.quirks());
+    if let Some(seed) = seed {
+        chip.set_seed(seed);
+    }
+    chip.set_tracer(tracer);
+
+    let mut runner = Headless::new(chip, config.ipf, script);
+    runner.set_recorder(recorder);
+    if let Some(path) = video {
+        let (width, height) = runner.chip.resolution();
+        match VideoRecorder::create(
+            Path::new(&path),
+            width,
+            height,
+            scale.unwrap_or(DEFAULT_SCALE),
+            [255; 3],
+            [0; 3],
+        ) {
+            Ok(v) => runner.set_video(Some(v)),
+            Err(e) => fail(&format!("Failed to create {path}: {e}")),
+        }
+    }
+    let result = crash::catch(|| match cycles {
+        Some(n) => runner.run_cycles(n),
+        None => runner.run_frames(frames),
+    });
+    runner.chip.set_tracer(None);
+    runner.set_recorder(None);
+    runner.set_video(None);
+
+    // Outputs are written even on error, they help to understand what happened
+    if screen.is_none() && screenshot.is_none() && state.is_none() {
+        let (width, height) = runner.chip.resolution();
+        for line in image::to_ascii(runner.chip.get_framebuffer(), width, height) {
+            println!("{line}");
+        }
+    }
+    if let Some(path) = screen {
+        if let Err(e) = write_screen(&runner.chip, Path::new(&path), scale.unwrap_or(1)) {
+            fail(&format!("Failed to write {path}: {e}"));
+        }
+    }
+    if let Some(dir) = screenshot {
+        let path = timestamp::file_name(Path::new(&dir), "chip8", "png");
+        if let Err(e) = write_screen(&runner.chip, &path, scale.unwrap_or(1)) {
+            fail(&format!("Failed to write {}: {e}", path.display()));
+        }
+        println!("{}", path.display());
+    }
+    if let Some(path) = state {
+        if let Err(e) = fs::write(&path, runner.state_json() + "\n") {
+            fail(&format!("Failed to write {path}: {e}"));
+        }
+    }
+
+    if let (Err(failure), Some(dir)) = (&result, crash_dir) {
+        match crash::write_bundle(
+            Path::new(&dir),
+            &runner.chip,
+            failure,
+            Path::new(rom),
+            &bytes,
+            &config.to_toml(),
+        ) {
+            Ok(path) => eprintln!("Crash bundle written in {}", path.display()),
+            Err(e) => eprintln!("Failed to write the crash bundle in {dir}: {e}"),
+        }
+    }
+    if let Err(e) = result {
+        eprintln!("Emulation stopped after {} cycles: {e}", runner.cycles());
+        exit(1);
+    }
+}
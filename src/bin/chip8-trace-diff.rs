@@ -0,0 +1,92 @@
+use chip8_emulator::trace::{first_divergence, read_trace, Record};
+use std::env;
+use std::path::Path;
+use std::process::exit;
+
+fn usage() -> ! {
+    eprintln!("usage: chip8-trace-diff [--ignore <FIELD,...>] <OUR TRACE> <REFERENCE TRACE>");
+    exit(2);
+}
+
+/// Print a record with its fields in the order of the trace format
+fn show(record: &Option<Record>) -> String {
+    let Some(record) = record else {
+        return "<end of trace>".to_string();
+    };
+
+    let order = ["cycle", "pc", "opcode", "i", "dt", "st"];
+    let hex = ["pc", "opcode", "i"];
+
+    let mut fields: Vec<(&String, &u64)> = record.iter().collect();
+    fields.sort_by_key(|(name, _)| order.iter().position(|o| o == name).unwrap_or(order.len()));
+
+    fields
+        .iter()
+        .map(|(name, value)| {
+            if hex.contains(&name.as_str()) {
+                format!("{name}={value:#06x}")
+            } else {
+                format!("{name}={value}")
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut ignore = vec![];
+    let mut files = vec![];
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ignore" => match args.next() {
+                Some(fields) => ignore.extend(fields.split(',').map(|f| f.trim().to_lowercase())),
+                None => usage(),
+            },
+            "-h" | "--help" => usage(),
+            _ => files.push(arg),
+        }
+    }
+
+    if files.len() != 2 {
+        usage();
+    }
+
+    let traces: Vec<Vec<Record>> = files
+        .iter()
+        .map(|f| match read_trace(Path::new(f)) {
+            Ok(trace) => trace,
+            Err(e) => {
+                eprintln!("Failed to read {f}: {e}");
+                exit(2);
+            }
+        })
+        .collect();
+
+    match first_divergence(&traces[0], &traces[1], &ignore) {
+        None => println!("Traces are identical ({} records)", traces[0].len()),
+        Some(d) => {
+            let cycle = d
+                .ours
+                .as_ref()
+                .or(d.reference.as_ref())
+                .and_then(|r| r.get("cycle").copied())
+                .unwrap_or(d.index as u64);
+
+            if d.fields.is_empty() {
+                println!("Traces diverge at cycle {cycle}: one trace is shorter");
+            } else {
+                println!(
+                    "Traces diverge at cycle {cycle} on: {}",
+                    d.fields.join(", ")
+                );
+            }
+            println!("  ours      : {}", show(&d.ours));
+            println!("  reference : {}", show(&d.reference));
+            exit(1);
+        }
+    }
+}
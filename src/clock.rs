@@ -0,0 +1,39 @@
+//! ## Emulation clock
+//! The machine runs in ticks of 1/60 s: the instructions of a frame, then
+//! the timers are decremented. The frontend renders at its own rate, so the
+//! clock tells how many ticks fit in the time spent since the last render.
+//! The speed multiplies this time, it is above 1 to fast-forward and below 1
+//! for slow motion.
+
+/// Duration of a tick in seconds
+pub const TICK: f64 = 1.0 / 60.0;
+
+/// Longest time run at once, so the emulation doesn't try to catch up after
+/// the window was blocked
+const MAX_ELAPSED: f64 = 0.25;
+
+#[derive(Clone, Debug, Default)]
+pub struct Clock {
+    /// Time not run yet, in ticks
+    pending: f64,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Clock::default()
+    }
+
+    /// Add `elapsed` seconds of real time at `speed` times the normal speed
+    /// and return the number of ticks to run
+    pub fn advance(&mut self, elapsed: f64, speed: f64) -> u32 {
+        self.pending += elapsed.clamp(0.0, MAX_ELAPSED) * speed / TICK;
+        let ticks = self.pending.floor();
+        self.pending -= ticks;
+        ticks as u32
+    }
+
+    /// Forget the time not run yet, when the emulation is paused
+    pub fn reset(&mut self) {
+        self.pending = 0.0;
+    }
+}
@@ -0,0 +1,407 @@
+//! ## Configuration of the frontend
+//! Options come from, by increasing priority:
+//! - their default value,
+//! - the top level of a TOML file,
+//! - the table of the ROM in this file, `[rom."<file name of the ROM>"]`,
+//! - the command line.
+//!
+//! An option has the same name in the file and on the command line, for
+//! example `ipf = 10` and `--ipf 10`:
+//!
+//! ```toml
+//! scale = 15
+//! palette = "amber"
+//! keymap = "qwerty"
+//!
+//! [rom."pong.ch8"]
+//! ipf = 10
+//! quirks = "vip"
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use toml::{Table, Value};
+
+use crate::audio::{Waveform, DEFAULT_FREQUENCY, DEFAULT_VOLUME};
+use crate::effects::{Overlays, Persistence, DEFAULT_FADE_FRAMES};
+use crate::emulator::{Quirks, HISTORY_SIZE};
+use crate::headless::DEFAULT_IPF;
+use crate::palette::{Palette, MAX_COLORS};
+
+/// Configuration file read when none is given on the command line
+pub const CONFIG_FILE: &str = "chip8.toml";
+
+/// The machine a ROM is written for
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Variant {
+    #[default]
+    Chip8,
+    Schip,
+    XoChip,
+}
+
+impl Variant {
+    /// Quirks used when no preset is given
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Variant::Chip8 => Quirks::default(),
+            Variant::Schip => Quirks::SCHIP,
+            Variant::XoChip => Quirks::XOCHIP,
+        }
+    }
+
+    /// Variant of a ROM given by the extension of its file: `.sc8` for
+    /// SCHIP, `.xo8` for XO-CHIP and CHIP-8 otherwise
+    pub fn from_path(path: &Path) -> Variant {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("sc8") => Variant::Schip,
+            Some(ext) if ext.eq_ignore_ascii_case("xo8") => Variant::XoChip,
+            _ => Variant::Chip8,
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chip8" => Ok(Variant::Chip8),
+            "schip" => Ok(Variant::Schip),
+            "xochip" => Ok(Variant::XoChip),
+            _ => Err(format!("unknown variant '{s}' (chip8, schip or xochip)")),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Variant::Chip8 => "chip8",
+            Variant::Schip => "schip",
+            Variant::XoChip => "xochip",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// Initial size of a CHIP-8 pixel in the window
+    pub scale: i32,
+    /// Round the size of pixels to an integer
+    pub integer_scale: bool,
+    pub fullscreen: bool,
+    /// Instructions per frame, there are 60 frames per second
+    pub ipf: usize,
+    /// Speed while fast-forwarding, 0 runs as fast as possible
+    pub fast_forward: f64,
+    /// Speed in slow motion, below 1
+    pub slow_motion: f64,
+    pub palette: Palette,
+    /// Colour of pixels that are set, overrides the one of the palette
+    pub foreground: Option<[u8; 3]>,
+    /// Colour of pixels that are not set, overrides the one of the palette
+    pub background: Option<[u8; 3]>,
+    pub persistence: Persistence,
+    /// Number of frames a pixel takes to fade out
+    pub fade_frames: usize,
+    pub overlays: Overlays,
+    /// Quirks preset, the one of the variant is used if it is not set
+    pub quirks: Option<Quirks>,
+    /// Name of a keymap preset or path of a keymap file
+    pub keymap: String,
+    pub seed: Option<u64>,
+    pub variant: Variant,
+    /// Start with the emulation paused
+    pub paused: bool,
+    /// Frequency of the beep in Hz
+    pub tone: f32,
+    /// Volume of the beep from 0 to 100
+    pub volume: u8,
+    pub waveform: Waveform,
+    pub video_scale: usize,
+    pub screenshot_scale: usize,
+    /// Number of executed instructions kept for crash reports
+    pub history: usize,
+    /// Reload the ROM when its file changes
+    pub watch: bool,
+    /// Directory listed by the ROM browser
+    pub rom_dir: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            scale: 20,
+            integer_scale: false,
+            fullscreen: false,
+            ipf: DEFAULT_IPF,
+            fast_forward: 0.0,
+            slow_motion: 0.25,
+            palette: Palette::default(),
+            foreground: None,
+            background: None,
+            persistence: Persistence::default(),
+            fade_frames: DEFAULT_FADE_FRAMES,
+            overlays: Overlays::default(),
+            quirks: None,
+            keymap: "azerty".to_string(),
+            seed: None,
+            variant: Variant::default(),
+            paused: false,
+            tone: DEFAULT_FREQUENCY,
+            volume: (DEFAULT_VOLUME * 100.0) as u8,
+            waveform: Waveform::default(),
+            video_scale: crate::video::DEFAULT_SCALE,
+            screenshot_scale: 1,
+            history: HISTORY_SIZE,
+            watch: false,
+            rom_dir: "chip8-roms".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Quirks to use, either the preset that is set or the ones of the
+    /// variant
+    pub fn quirks(&self) -> Quirks {
+        self.quirks.unwrap_or(self.variant.quirks())
+    }
+
+    /// Palette to use, with the foreground and the background that are set
+    pub fn palette(&self) -> Palette {
+        let mut palette = self.palette.clone();
+        if let Some(color) = self.foreground {
+            palette.set_foreground(color);
+        }
+        if let Some(color) = self.background {
+            palette.set_background(color);
+        }
+        palette
+    }
+
+    /// Set the option `name` from its textual `value`
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let invalid = |what: &str| format!("{name}: expected {what}, got '{value}'");
+
+        match name {
+            "scale" => self.scale = positive(value).ok_or_else(|| invalid("a positive number"))?,
+            "ipf" => self.ipf = positive(value).ok_or_else(|| invalid("a positive number"))?,
+            "integer-scale" => {
+                self.integer_scale = value.parse().map_err(|_| invalid("true or false"))?
+            }
+            "fullscreen" => {
+                self.fullscreen = value.parse().map_err(|_| invalid("true or false"))?
+            }
+            "fast-forward" => {
+                self.fast_forward = value
+                    .parse()
+                    .ok()
+                    .filter(|s: &f64| *s == 0.0 || *s > 1.0)
+                    .ok_or_else(|| invalid("0 or a speed above 1"))?
+            }
+            "slow-motion" => {
+                self.slow_motion = value
+                    .parse()
+                    .ok()
+                    .filter(|s: &f64| *s > 0.0 && *s < 1.0)
+                    .ok_or_else(|| invalid("a speed between 0 and 1"))?
+            }
+            "foreground" => {
+                self.foreground = Some(parse_color(value).map_err(|e| format!("{name}: {e}"))?)
+            }
+            "background" => {
+                self.background = Some(parse_color(value).map_err(|e| format!("{name}: {e}"))?)
+            }
+            "palette" => self.palette = value.parse().map_err(|e| format!("{name}: {e}"))?,
+            "persistence" => {
+                self.persistence = value.parse().map_err(|e| format!("{name}: {e}"))?
+            }
+            "fade-frames" => {
+                self.fade_frames = positive(value).ok_or_else(|| invalid("a positive number"))?
+            }
+            "scanlines" => {
+                self.overlays.scanlines = value.parse().map_err(|_| invalid("true or false"))?
+            }
+            "grid" => self.overlays.grid = value.parse().map_err(|_| invalid("true or false"))?,
+            "quirks" => self.quirks = Some(value.parse().map_err(|e| format!("{name}: {e}"))?),
+            "keymap" => self.keymap = value.to_string(),
+            "seed" => {
+                // TOML integers are signed, bigger seeds couldn't be saved
+                let seed = value
+                    .parse()
+                    .ok()
+                    .filter(|s: &u64| *s <= i64::MAX as u64)
+                    .ok_or_else(|| invalid(&format!("a number up to {}", i64::MAX)))?;
+                self.seed = Some(seed)
+            }
+            "variant" => self.variant = value.parse().map_err(|e| format!("{name}: {e}"))?,
+            "paused" => self.paused = value.parse().map_err(|_| invalid("true or false"))?,
+            "tone" => {
+                self.tone = value
+                    .parse()
+                    .ok()
+                    .filter(|hz: &f32| *hz > 0.0)
+                    .ok_or_else(|| invalid("a frequency in Hz"))?
+            }
+            "volume" => {
+                self.volume = value
+                    .parse()
+                    .ok()
+                    .filter(|v| *v <= 100)
+                    .ok_or_else(|| invalid("a value from 0 to 100"))?
+            }
+            "waveform" => self.waveform = value.parse().map_err(|e| format!("{name}: {e}"))?,
+            "video-scale" => {
+                self.video_scale = positive(value).ok_or_else(|| invalid("a positive number"))?
+            }
+            "screenshot-scale" => {
+                self.screenshot_scale =
+                    positive(value).ok_or_else(|| invalid("a positive number"))?
+            }
+            "history" => self.history = value.parse().map_err(|_| invalid("a number"))?,
+            "watch" => self.watch = value.parse().map_err(|_| invalid("true or false"))?,
+            "rom-dir" => self.rom_dir = value.to_string(),
+            _ => return Err(format!("{name}: unknown option")),
+        }
+        Ok(())
+    }
+
+    /// Return the options as a TOML document, it can be read back to get
+    /// the same configuration
+    pub fn to_toml(&self) -> String {
+        let mut table = Table::new();
+        let mut set = |name: &str, value: Value| {
+            table.insert(name.to_string(), value);
+        };
+        let color = |c: [u8; 3]| Value::String(format!("#{:02X}{:02X}{:02X}", c[0], c[1], c[2]));
+
+        set("scale", Value::Integer(self.scale as i64));
+        set("integer-scale", Value::Boolean(self.integer_scale));
+        set("fullscreen", Value::Boolean(self.fullscreen));
+        set("ipf", Value::Integer(self.ipf as i64));
+        set("fast-forward", Value::Float(self.fast_forward));
+        set("slow-motion", Value::Float(self.slow_motion));
+        if Palette::preset(self.palette.name()) == Some(self.palette.clone()) {
+            set("palette", Value::String(self.palette.name().to_string()));
+        } else {
+            let colors = (0..MAX_COLORS).map(|i| color(self.palette.color(i)));
+            set("palette", Value::Array(colors.collect()));
+        }
+        if let Some(c) = self.foreground {
+            set("foreground", color(c));
+        }
+        if let Some(c) = self.background {
+            set("background", color(c));
+        }
+        set("persistence", Value::String(self.persistence.to_string()));
+        set("fade-frames", Value::Integer(self.fade_frames as i64));
+        set("scanlines", Value::Boolean(self.overlays.scanlines));
+        set("grid", Value::Boolean(self.overlays.grid));
+        if let Some(quirks) = self.quirks {
+            let name = Quirks::PRESETS
+                .into_iter()
+                .find(|name| name.parse() == Ok(quirks))
+                .unwrap_or("default");
+            set("quirks", Value::String(name.to_string()));
+        }
+        set("keymap", Value::String(self.keymap.clone()));
+        if let Some(seed) = self.seed {
+            set("seed", Value::Integer(seed as i64));
+        }
+        set("variant", Value::String(self.variant.to_string()));
+        set("paused", Value::Boolean(self.paused));
+        set("tone", Value::Float(self.tone as f64));
+        set("volume", Value::Integer(self.volume as i64));
+        set("waveform", Value::String(self.waveform.to_string()));
+        set("video-scale", Value::Integer(self.video_scale as i64));
+        set(
+            "screenshot-scale",
+            Value::Integer(self.screenshot_scale as i64),
+        );
+        set("history", Value::Integer(self.history as i64));
+        set("watch", Value::Boolean(self.watch));
+        set("rom-dir", Value::String(self.rom_dir.clone()));
+        table.to_string()
+    }
+
+    /// Apply the options of the TOML file at `path`, then the ones of the
+    /// table of `rom`
+    pub fn load(&mut self, path: &Path, rom: &Path) -> Result<(), String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        self.apply_toml(&content, rom)
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Apply the options of a TOML document, then the ones of the table of
+    /// `rom`
+    pub fn apply_toml(&mut self, content: &str, rom: &Path) -> Result<(), String> {
+        let table: Table = content.parse().map_err(|e| format!("{e}"))?;
+
+        for (name, value) in &table {
+            if name != "rom" {
+                self.set_value(name, value)?;
+            }
+        }
+
+        let Some(roms) = table.get("rom") else {
+            return Ok(());
+        };
+        let Value::Table(roms) = roms else {
+            return Err("rom: expected a table of ROMs".to_string());
+        };
+        let file_name = rom.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+        match roms.get(file_name) {
+            Some(Value::Table(options)) => {
+                for (name, value) in options {
+                    self.set_value(name, value)
+                        .map_err(|e| format!("rom.\"{file_name}\".{e}"))?;
+                }
+                Ok(())
+            }
+            Some(_) => Err(format!("rom.\"{file_name}\": expected a table")),
+            None => Ok(()),
+        }
+    }
+
+    fn set_value(&mut self, name: &str, value: &Value) -> Result<(), String> {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Integer(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Boolean(b) => b.to_string(),
+            // Lists like the colours of a palette
+            Value::Array(values) => values
+                .iter()
+                .map(|v| v.as_str().map(str::to_string).unwrap_or(v.to_string()))
+                .collect::<Vec<_>>()
+                .join(","),
+            _ => return Err(format!("{name}: expected a string, a number or a boolean")),
+        };
+        self.set(name, &value)
+    }
+}
+
+fn positive<T: FromStr + Default + PartialOrd>(value: &str) -> Option<T> {
+    value.parse().ok().filter(|v| *v > T::default())
+}
+
+/// Parse a colour written `#RRGGBB` or `RRGGBB`
+pub fn parse_color(s: &str) -> Result<[u8; 3], String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(format!("expected a colour like #00FF00, got '{s}'"));
+    }
+
+    let mut rgb = [0; 3];
+    for (i, c) in rgb.iter_mut().enumerate() {
+        *c = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| format!("expected a colour like #00FF00, got '{s}'"))?;
+    }
+    Ok(rgb)
+}
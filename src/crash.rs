@@ -0,0 +1,197 @@
+//! ## Crash bundles
+//! When a ROM fails, with a [`Chip8Error`] or a panic of the emulator, the
+//! frontends write a directory with what is needed to reproduce it:
+//! - `error.txt`: the error and the report shown in the window,
+//! - `state.json`: the registers, the stack, the timers, the quirks, the
+//!   memory and the screen,
+//! - `history.txt`: the last executed instructions with the registers they
+//!   changed,
+//! - `rom.txt`: the name, the size and the SHA-1 of the ROM, which is copied
+//!   next to it,
+//! - `config.toml`: the effective configuration.
+
+use std::any::Any;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+
+use crate::disasm;
+use crate::emulator::{Chip8, Chip8Error};
+use crate::sha1;
+use crate::timestamp;
+
+/// What stopped the machine
+#[derive(Clone, PartialEq, Debug)]
+pub enum Failure {
+    Error(Chip8Error),
+    /// A panic of the emulator, with its message
+    Panic(String),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Error(e) => write!(f, "{e}"),
+            Failure::Panic(msg) => write!(f, "Emulator panicked: {msg}"),
+        }
+    }
+}
+
+impl From<Chip8Error> for Failure {
+    fn from(e: Chip8Error) -> Self {
+        Failure::Error(e)
+    }
+}
+
+/// Run `f` and turn its error or its panic into a [`Failure`]
+pub fn catch<T>(f: impl FnOnce() -> Result<T, Chip8Error>) -> Result<T, Failure> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result.map_err(Failure::Error),
+        Err(payload) => Err(Failure::Panic(panic_message(payload))),
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Return the lines that describe the machine after `failure`, with the
+/// last `history_lines` executed instructions
+pub fn report(chip: &Chip8, failure: &Failure, history_lines: usize) -> Vec<String> {
+    let mut lines = vec![format!("Error: {failure}")];
+    let pc = match (failure, chip.history().back()) {
+        // The failing instruction is the last one of the history
+        (Failure::Error(_), Some(last)) => last.pc,
+        _ => chip.pc(),
+    };
+    let opcode = chip.opcode_at(pc);
+    lines.push(format!(
+        "PC {pc:#06x}  opcode {opcode:#06x}  {}",
+        disasm::mnemonic(opcode)
+    ));
+    for (half, regs) in chip.vregs().chunks(8).enumerate() {
+        let regs: Vec<String> = regs
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("V{:X} {v:02x}", half * 8 + i))
+            .collect();
+        lines.push(regs.join("  "));
+    }
+    let stack: Vec<String> = chip.stack().iter().map(|a| format!("{a:#06x}")).collect();
+    lines.push(format!(
+        "I {:#06x}  DT {}  ST {}  stack [{}]",
+        chip.i(),
+        chip.delay_timer(),
+        chip.sound_timer(),
+        stack.join(", ")
+    ));
+    lines.push(String::new());
+    lines.push("Recent instructions:".to_string());
+    lines.extend(
+        history(chip, history_lines)
+            .into_iter()
+            .map(|l| format!("  {l}")),
+    );
+    lines
+}
+
+/// Return the last `count` executed instructions, the oldest first, with
+/// the registers they changed
+pub fn history(chip: &Chip8, count: usize) -> Vec<String> {
+    let history = chip.history();
+    history
+        .iter()
+        .skip(history.len().saturating_sub(count))
+        .map(|e| {
+            let line = format!(
+                "{:#06x}  {:04x}  {:<16}",
+                e.pc,
+                e.opcode,
+                disasm::mnemonic(e.opcode)
+            );
+            format!("{line}{}", e.effects().join(" "))
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Return the state of the machine as JSON. The memory and the screen are
+/// written in hexadecimal.
+pub fn state_json(chip: &Chip8) -> serde_json::Value {
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    let quirks = chip.quirks();
+    let (width, height) = chip.resolution();
+    json!({
+        "pc": format!("{:#06x}", chip.pc()),
+        "i": format!("{:#06x}", chip.i()),
+        "v": chip.vregs(),
+        "stack": chip.stack().iter().map(|a| format!("{a:#06x}")).collect::<Vec<String>>(),
+        "dt": chip.delay_timer(),
+        "st": chip.sound_timer(),
+        "keys": chip.keyboard(),
+        "quirks": {
+            "shift_vy": quirks.shift_vy,
+            "load_store_increment_i": quirks.load_store_increment_i,
+            "jump_vx": quirks.jump_vx,
+            "vf_reset": quirks.vf_reset,
+        },
+        "resolution": [width, height],
+        "memory": hex(chip.memory()),
+        "framebuffer": hex(chip.get_framebuffer()),
+    })
+}
+
+/// Write a crash bundle in a new directory of `dir` named after the current
+/// time, and return its path. `rom` is the path of the ROM and `bytes` its
+/// content, `config` is the effective configuration as TOML.
+pub fn write_bundle(
+    dir: &Path,
+    chip: &Chip8,
+    failure: &Failure,
+    rom: &Path,
+    bytes: &[u8],
+    config: &str,
+) -> io::Result<PathBuf> {
+    let path = timestamp::file_name(dir, "chip8-crash", "");
+    fs::create_dir_all(&path)?;
+
+    let all = chip.history().len();
+    fs::write(
+        path.join("error.txt"),
+        report(chip, failure, all).join("\n") + "\n",
+    )?;
+    fs::write(
+        path.join("state.json"),
+        serde_json::to_string_pretty(&state_json(chip)).unwrap() + "\n",
+    )?;
+    fs::write(
+        path.join("history.txt"),
+        history(chip, all).join("\n") + "\n",
+    )?;
+
+    let name = rom
+        .file_name()
+        .map_or("rom".into(), |n| n.to_string_lossy());
+    fs::write(
+        path.join("rom.txt"),
+        format!(
+            "name: {name}\nsize: {}\nsha1: {}\n",
+            bytes.len(),
+            sha1::hex_digest(bytes)
+        ),
+    )?;
+    fs::write(path.join(name.as_ref()), bytes)?;
+    fs::write(path.join("config.toml"), config)?;
+    Ok(path)
+}
@@ -0,0 +1,709 @@
+//! ## Debug Adapter Protocol server
+//! ### Links
+//! - [Specification](https://microsoft.github.io/debug-adapter-protocol/specification)
+//!
+//! The server talks DAP on stdin/stdout and drives a [`Chip8`] with `step()`.
+//! Logs are written on stderr so they don't mess with the protocol.
+//!
+//! ### Launch arguments
+//! - `program`: the ROM to run (required)
+//! - `source`: the source file the ROM was assembled from (Octo, ...)
+//! - `sourceMap`: a file that maps addresses to lines of `source`. Each line
+//!   is `<hex address> <line>`, empty lines and lines starting with `#` are
+//!   ignored.
+//! - `stopOnEntry`: stop before executing the first instruction
+//! - `trace`: write a trace of executed instructions in this file
+//! - `database`: settings of known ROMs (see [`crate::database`]), the
+//!   default database is used without it. A known ROM runs with its quirks
+//!   and its instructions per frame.
+//!
+//! The `restart` request does a hard reset of the machine (see
+//! [`Chip8::hard_reset`]) and keeps the breakpoints.
+//!
+//! Without a source map, breakpoints can still be set on addresses using
+//! instruction breakpoints.
+//!
+//! ### Variables
+//! - Registers: V0 to VF, I, PC and SP (the depth of the stack)
+//! - Timers: DT and ST
+//! - Display: one line per row of pixels
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::database::Database;
+use crate::emulator::Chip8;
+use crate::image;
+use crate::trace::Tracer;
+
+/// CHIP-8 has only one thread of execution
+const THREAD_ID: u64 = 1;
+/// Instructions executed between two checks of incoming requests, unless the
+/// database gives the instructions per frame of the ROM. A slice is a frame
+/// of the frontend: timers are decremented after each one.
+const INSNS_PER_SLICE: usize = 6;
+const SLICE_DURATION: Duration = Duration::from_micros(16_667);
+/// Biggest message accepted from the client, requests are much smaller
+const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+/// References given to the scopes returned by the "scopes" request
+const REGISTERS_REF: u64 = 1;
+const TIMERS_REF: u64 = 2;
+const DISPLAY_REF: u64 = 3;
+
+/// Why the machine is executing instructions
+enum Mode {
+    /// Until a breakpoint or a pause request
+    Continue,
+    /// Until we return to `pc` with a stack of `depth` entries. Used to step
+    /// over subroutine calls.
+    StepOver { pc: usize, depth: usize },
+    /// Until the stack has less than `depth` entries
+    StepOut { depth: usize },
+}
+
+/// Addresses of a ROM mapped to lines of its source file
+struct SourceMap {
+    source: PathBuf,
+    lines: BTreeMap<usize, u64>,
+}
+
+impl SourceMap {
+    fn load(source: PathBuf, from: &Path) -> io::Result<Self> {
+        let mut lines = BTreeMap::new();
+
+        for (n, l) in fs::read_to_string(from)?.lines().enumerate() {
+            let l = l.trim();
+            if l.is_empty() || l.starts_with('#') {
+                continue;
+            }
+
+            let parsed = l.split_once(char::is_whitespace).and_then(|(addr, line)| {
+                let addr = addr.trim_start_matches("0x").trim_start_matches("0X");
+                Some((
+                    usize::from_str_radix(addr, 16).ok()?,
+                    line.trim().parse::<u64>().ok()?,
+                ))
+            });
+
+            match parsed {
+                Some((addr, line)) => {
+                    lines.insert(addr, line);
+                }
+                None => log::warn!("{}:{}: invalid entry '{l}'", from.display(), n + 1),
+            }
+        }
+
+        Ok(SourceMap { source, lines })
+    }
+
+    /// Return the line of the instruction at `addr`
+    fn line(&self, addr: usize) -> Option<u64> {
+        self.lines.range(..=addr).next_back().map(|(_, line)| *line)
+    }
+
+    /// Return the lowest address generated by `line`
+    fn address(&self, line: u64) -> Option<usize> {
+        self.lines
+            .iter()
+            .find(|(_, l)| **l == line)
+            .map(|(addr, _)| *addr)
+    }
+
+    fn is_source(&self, path: &str) -> bool {
+        match (fs::canonicalize(path), fs::canonicalize(&self.source)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => Path::new(path) == self.source,
+        }
+    }
+}
+
+pub struct DapServer<W: Write> {
+    out: W,
+    /// Sequence number of the next message we send
+    seq: u64,
+    chip: Chip8,
+    source_map: Option<SourceMap>,
+    /// Breakpoints set on source lines, already translated to addresses
+    line_breakpoints: BTreeSet<usize>,
+    /// Breakpoints set on addresses
+    insn_breakpoints: BTreeSet<usize>,
+    stop_on_entry: bool,
+    insns_per_slice: usize,
+    /// Set while the machine is running
+    mode: Option<Mode>,
+    /// Set when a stop was reported: the first instruction executed after
+    /// it doesn't stop on its breakpoint
+    resuming: bool,
+}
+
+impl<W: Write> DapServer<W> {
+    pub fn new(out: W) -> Self {
+        DapServer {
+            out,
+            seq: 1,
+            chip: Chip8::default(),
+            source_map: None,
+            line_breakpoints: BTreeSet::new(),
+            insn_breakpoints: BTreeSet::new(),
+            stop_on_entry: false,
+            insns_per_slice: INSNS_PER_SLICE,
+            mode: None,
+            resuming: false,
+        }
+    }
+
+    /// Serve requests read from `input` until the client disconnects.
+    pub fn run<R: Read + Send + 'static>(mut self, input: R) -> io::Result<()> {
+        // Requests are read by another thread so we can check if one
+        // arrived (a pause for example) while the machine is running.
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(input);
+            loop {
+                match read_message(&mut reader) {
+                    Ok(Some(msg)) => {
+                        if tx.send(msg).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::error!("Failed to read request: {e}");
+                        break;
+                    }
+                }
+            }
+        });
+
+        loop {
+            let msg = if self.mode.is_some() {
+                match rx.try_recv() {
+                    Ok(msg) => Some(msg),
+                    Err(mpsc::TryRecvError::Empty) => None,
+                    Err(mpsc::TryRecvError::Disconnected) => return Ok(()),
+                }
+            } else {
+                match rx.recv() {
+                    Ok(msg) => Some(msg),
+                    Err(_) => return Ok(()),
+                }
+            };
+
+            match msg {
+                Some(msg) => {
+                    if !self.handle(&msg)? {
+                        return Ok(());
+                    }
+                }
+                None => {
+                    self.run_slice()?;
+                    thread::sleep(SLICE_DURATION);
+                }
+            }
+        }
+    }
+
+    /// Execute a few instructions and report a stop if any
+    fn run_slice(&mut self) -> io::Result<()> {
+        for _ in 0..self.insns_per_slice {
+            let pc = self.chip.pc();
+            let on_breakpoint =
+                self.line_breakpoints.contains(&pc) || self.insn_breakpoints.contains(&pc);
+            if on_breakpoint && !self.resuming {
+                self.mode = None;
+                return self.send_stopped("breakpoint", None);
+            }
+            self.resuming = false;
+
+            if let Err(e) = self.chip.step() {
+                log::error!("{e}");
+                self.mode = None;
+                self.send_output(&format!("{e}\n"))?;
+                return self.send_stopped("exception", Some(&e.to_string()));
+            }
+
+            let pc = self.chip.pc();
+            let depth = self.chip.stack().len();
+            let done = match self.mode {
+                Some(Mode::StepOver { pc: ret, depth: d }) => pc == ret && depth == d,
+                Some(Mode::StepOut { depth: d }) => depth < d,
+                _ => false,
+            };
+            if done {
+                self.mode = None;
+                return self.send_stopped("step", None);
+            }
+        }
+        self.chip.tick_timers();
+
+        Ok(())
+    }
+
+    /// Handle a message sent by the client. Return false when the session
+    /// is over.
+    fn handle(&mut self, msg: &Value) -> io::Result<bool> {
+        let command = msg["command"].as_str().unwrap_or_default();
+        let args = &msg["arguments"];
+
+        log::debug!("dap <- {msg}");
+
+        match command {
+            "initialize" => {
+                let capabilities = json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsInstructionBreakpoints": true,
+                    "supportsReadMemoryRequest": true,
+                    "supportsRestartRequest": true,
+                    "supportsTerminateRequest": true,
+                });
+                self.respond(msg, Ok(capabilities))?;
+            }
+            "launch" => {
+                let result = self.launch(args);
+                let launched = result.is_ok();
+                self.respond(msg, result)?;
+                if launched {
+                    // Breakpoints can be translated now that we have the source map
+                    self.send_event("initialized", json!({}))?;
+                }
+            }
+            "setBreakpoints" => {
+                let body = self.set_breakpoints(args);
+                self.respond(msg, Ok(body))?;
+            }
+            "setInstructionBreakpoints" => {
+                let body = self.set_instruction_breakpoints(args);
+                self.respond(msg, Ok(body))?;
+            }
+            "setExceptionBreakpoints" => {
+                self.respond(msg, Ok(json!({ "breakpoints": [] })))?;
+            }
+            "configurationDone" => {
+                self.respond(msg, Ok(json!({})))?;
+                if self.stop_on_entry {
+                    self.send_stopped("entry", None)?;
+                } else {
+                    self.mode = Some(Mode::Continue);
+                }
+            }
+            "threads" => {
+                let threads = json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] });
+                self.respond(msg, Ok(threads))?;
+            }
+            "stackTrace" => {
+                let body = self.stack_trace();
+                self.respond(msg, Ok(body))?;
+            }
+            "scopes" => {
+                let scopes = json!({ "scopes": [
+                    { "name": "Registers", "variablesReference": REGISTERS_REF, "expensive": false },
+                    { "name": "Timers", "variablesReference": TIMERS_REF, "expensive": false },
+                    { "name": "Display", "variablesReference": DISPLAY_REF, "expensive": true },
+                ]});
+                self.respond(msg, Ok(scopes))?;
+            }
+            "variables" => {
+                let body = self.variables(args["variablesReference"].as_u64().unwrap_or(0));
+                self.respond(msg, Ok(body))?;
+            }
+            "readMemory" => {
+                let body = self.read_memory(args);
+                self.respond(msg, body)?;
+            }
+            "continue" => {
+                self.mode = Some(Mode::Continue);
+                self.respond(msg, Ok(json!({ "allThreadsContinued": true })))?;
+            }
+            "next" => {
+                self.respond(msg, Ok(json!({})))?;
+                let pc = self.chip.pc();
+                let opcode = self.chip.opcode_at(pc);
+                if opcode & 0xF000 == 0x2000 {
+                    self.mode = Some(Mode::StepOver {
+                        pc: pc + 2,
+                        depth: self.chip.stack().len(),
+                    });
+                } else {
+                    self.step_once()?;
+                }
+            }
+            "stepIn" => {
+                self.respond(msg, Ok(json!({})))?;
+                self.step_once()?;
+            }
+            "stepOut" => {
+                self.respond(msg, Ok(json!({})))?;
+                let depth = self.chip.stack().len();
+                if depth == 0 {
+                    // Not in a subroutine, there is nothing to step out of
+                    self.step_once()?;
+                } else {
+                    self.mode = Some(Mode::StepOut { depth });
+                }
+            }
+            "restart" => {
+                self.mode = None;
+                self.chip.hard_reset();
+                self.resuming = false;
+                self.respond(msg, Ok(json!({})))?;
+                if self.stop_on_entry {
+                    self.send_stopped("entry", None)?;
+                } else {
+                    self.mode = Some(Mode::Continue);
+                }
+            }
+            "pause" => {
+                self.respond(msg, Ok(json!({})))?;
+                if self.mode.take().is_some() {
+                    self.send_stopped("pause", None)?;
+                }
+            }
+            "terminate" => {
+                self.mode = None;
+                self.respond(msg, Ok(json!({})))?;
+                self.send_event("terminated", json!({}))?;
+            }
+            "disconnect" => {
+                self.respond(msg, Ok(json!({})))?;
+                return Ok(false);
+            }
+            _ => {
+                self.respond(msg, Err(format!("Unsupported request '{command}'")))?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn launch(&mut self, args: &Value) -> Result<Value, String> {
+        let Some(program) = args["program"].as_str() else {
+            return Err("'program' is required".to_string());
+        };
+
+        let rom = fs::read(program).map_err(|e| format!("Failed to load {program}: {e}"))?;
+        let database = Database::open(args["database"].as_str().map(Path::new))?;
+        let mut config = Config::default();
+        if let Some(info) = database.get(&rom) {
+            log::info!("{}", info.title);
+            info.apply(&mut config);
+        }
+
+        let mut chip = Chip8::default();
+        chip.load_bytes(&rom)
+            .map_err(|e| format!("Failed to load {program}: {e}"))?;
+        chip.set_quirks(config.quirks());
+
+        self.source_map = match (args["source"].as_str(), args["sourceMap"].as_str()) {
+            (Some(source), Some(map)) => Some(
+                SourceMap::load(PathBuf::from(source), Path::new(map))
+                    .map_err(|e| format!("Failed to read {map}: {e}"))?,
+            ),
+            (None, None) => None,
+            _ => return Err("'source' and 'sourceMap' go together".to_string()),
+        };
+
+        if let Some(trace) = args["trace"].as_str() {
+            let tracer = Tracer::create(Path::new(trace))
+                .map_err(|e| format!("Failed to create {trace}: {e}"))?;
+            chip.set_tracer(Some(tracer));
+        }
+
+        log::info!("Debugging {program}");
+        self.chip = chip;
+        self.insns_per_slice = config.ipf;
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+        self.resuming = false;
+
+        Ok(json!({}))
+    }
+
+    fn set_breakpoints(&mut self, args: &Value) -> Value {
+        let path = args["source"]["path"].as_str().unwrap_or_default();
+        let lines: Vec<u64> = args["breakpoints"]
+            .as_array()
+            .map(|bps| bps.iter().filter_map(|bp| bp["line"].as_u64()).collect())
+            .unwrap_or_default();
+
+        let map = self.source_map.as_ref().filter(|m| m.is_source(path));
+        self.line_breakpoints.clear();
+
+        let breakpoints: Vec<Value> = lines
+            .iter()
+            .map(|line| match map.and_then(|m| m.address(*line)) {
+                Some(addr) => {
+                    self.line_breakpoints.insert(addr);
+                    json!({ "verified": true, "line": line, "instructionReference": format!("{addr:#06x}") })
+                }
+                None => json!({ "verified": false, "line": line, "message": "No code at this line" }),
+            })
+            .collect();
+
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn set_instruction_breakpoints(&mut self, args: &Value) -> Value {
+        self.insn_breakpoints.clear();
+
+        let breakpoints: Vec<Value> = args["breakpoints"]
+            .as_array()
+            .map(|bps| bps.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|bp| {
+                let reference = bp["instructionReference"].as_str().unwrap_or_default();
+                let offset = bp["offset"].as_i64().unwrap_or(0);
+                match parse_address(reference).map(|a| a as i64 + offset) {
+                    Some(addr) if (0..self.chip.memory().len() as i64).contains(&addr) => {
+                        self.insn_breakpoints.insert(addr as usize);
+                        json!({ "verified": true, "instructionReference": format!("{addr:#06x}") })
+                    }
+                    _ => json!({ "verified": false, "message": "Invalid address" }),
+                }
+            })
+            .collect();
+
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn stack_trace(&self) -> Value {
+        // The current PC then the address of each call, innermost first
+        let pcs = std::iter::once(self.chip.pc())
+            .chain(self.chip.stack().iter().rev().map(|ret| ret - 2));
+
+        let frames: Vec<Value> = pcs
+            .enumerate()
+            .map(|(id, pc)| {
+                let mut frame = json!({
+                    "id": id,
+                    "name": format!("{pc:#06x}: {:04x}", self.chip.opcode_at(pc)),
+                    "line": 0,
+                    "column": 0,
+                    "instructionPointerReference": format!("{pc:#06x}"),
+                });
+                if let Some(map) = &self.source_map {
+                    if let Some(line) = map.line(pc) {
+                        frame["line"] = json!(line);
+                        frame["column"] = json!(1);
+                        frame["source"] = json!({
+                            "name": map.source.file_name().map(|n| n.to_string_lossy()),
+                            "path": map.source,
+                        });
+                    }
+                }
+                frame
+            })
+            .collect();
+
+        json!({ "totalFrames": frames.len(), "stackFrames": frames })
+    }
+
+    fn variables(&self, reference: u64) -> Value {
+        let byte = |name: String, v: u8| json!({ "name": name, "value": format!("{v:#04x} ({v})"), "variablesReference": 0 });
+        let address = |name: &str, v: usize| {
+            json!({
+                "name": name,
+                "value": format!("{v:#06x}"),
+                "variablesReference": 0,
+                "memoryReference": format!("{v:#06x}"),
+            })
+        };
+
+        let variables: Vec<Value> = match reference {
+            REGISTERS_REF => {
+                let mut vars: Vec<Value> = self
+                    .chip
+                    .vregs()
+                    .iter()
+                    .enumerate()
+                    .map(|(x, v)| byte(format!("V{x:X}"), *v))
+                    .collect();
+                vars.push(address("I", self.chip.i() as usize));
+                vars.push(address("PC", self.chip.pc()));
+                vars.push(byte("SP".to_string(), self.chip.stack().len() as u8));
+                vars
+            }
+            TIMERS_REF => vec![
+                byte("DT".to_string(), self.chip.delay_timer() as u8),
+                byte("ST".to_string(), self.chip.sound_timer() as u8),
+            ],
+            DISPLAY_REF => {
+                let (width, height) = self.chip.resolution();
+                image::to_ascii(self.chip.get_framebuffer(), width, height)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(y, pixels)| {
+                        json!({ "name": format!("{y:02}"), "value": pixels, "variablesReference": 0 })
+                    })
+                    .collect()
+            }
+            _ => vec![],
+        };
+
+        json!({ "variables": variables })
+    }
+
+    fn read_memory(&self, args: &Value) -> Result<Value, String> {
+        let reference = args["memoryReference"].as_str().unwrap_or_default();
+        let Some(base) = parse_address(reference) else {
+            return Err(format!("Invalid memory reference '{reference}'"));
+        };
+        let offset = args["offset"].as_i64().unwrap_or(0);
+        let Some(start) = (base as u64).checked_add_signed(offset) else {
+            return Err(format!("Invalid offset {offset} from '{reference}'"));
+        };
+        let count = args["count"].as_u64().unwrap_or(0);
+
+        let mem = self.chip.memory();
+        let first = start.min(mem.len() as u64) as usize;
+        let last = start.saturating_add(count).min(mem.len() as u64) as usize;
+        let data = &mem[first..last];
+
+        Ok(json!({
+            "address": format!("{start:#06x}"),
+            "data": base64(data),
+            "unreadableBytes": count - data.len() as u64,
+        }))
+    }
+
+    fn step_once(&mut self) -> io::Result<()> {
+        match self.chip.step() {
+            Ok(()) => self.send_stopped("step", None),
+            Err(e) => {
+                log::error!("{e}");
+                self.send_stopped("exception", Some(&e.to_string()))
+            }
+        }
+    }
+
+    fn respond(&mut self, request: &Value, result: Result<Value, String>) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => {
+                log::error!("{message}");
+                response["message"] = json!(message);
+            }
+        }
+        self.send(response)
+    }
+
+    fn send_stopped(&mut self, reason: &str, text: Option<&str>) -> io::Result<()> {
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        });
+        if let Some(text) = text {
+            body["description"] = json!(text);
+            body["text"] = json!(text);
+        }
+        self.resuming = true;
+        self.send_event("stopped", body)
+    }
+
+    fn send_output(&mut self, output: &str) -> io::Result<()> {
+        self.send_event("output", json!({ "category": "stderr", "output": output }))
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn send(&mut self, mut msg: Value) -> io::Result<()> {
+        msg["seq"] = json!(self.seq);
+        self.seq += 1;
+
+        log::debug!("dap -> {msg}");
+        let body = msg.to_string();
+        write!(self.out, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.out.flush()
+    }
+}
+
+/// Read a message framed with a Content-Length header. Return None at the
+/// end of the input.
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing Content-Length",
+        ));
+    };
+
+    if length > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Content-Length {length} is above {MAX_MESSAGE_SIZE}"),
+        ));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(io::Error::from)
+}
+
+/// Parse an address like "0x200" or "512"
+fn parse_address(s: &str) -> Option<usize> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(TABLE[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,205 @@
+//! ## ROM database
+//! A JSON file that gives the settings of known ROMs, in the spirit of the
+//! [CHIP-8 database](https://github.com/chip-8/chip-8-database). ROMs are
+//! identified by the SHA-1 of their content, so renamed files are still
+//! recognized. Every field but the title is optional:
+//!
+//! ```json
+//! {
+//!   "b232ef880bd6060fb45fa6effed7edf0ae95670e": {
+//!     "title": "Pong",
+//!     "author": "Paul Vervalin",
+//!     "platform": "chip8",
+//!     "quirks": "vip",
+//!     "ipf": 10,
+//!     "keys": { "1": "left paddle up", "4": "left paddle down" },
+//!     "palette": "classic"
+//!   }
+//! }
+//! ```
+//!
+//! The settings of a ROM are applied before the configuration file and the
+//! command line, so they can still be overridden.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::config::{Config, Variant};
+use crate::emulator::Quirks;
+use crate::palette::Palette;
+use crate::sha1;
+
+/// Database read when none is given on the command line
+pub const DATABASE_FILE: &str = "chip8-roms/database.json";
+
+/// What the database knows about a ROM
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RomInfo {
+    pub title: String,
+    pub author: Option<String>,
+    pub variant: Option<Variant>,
+    pub quirks: Option<Quirks>,
+    /// Instructions per frame
+    pub ipf: Option<usize>,
+    /// What the keys of the keypad do, sorted by key
+    pub keys: Vec<(u8, String)>,
+    pub palette: Option<Palette>,
+}
+
+impl RomInfo {
+    /// Apply the settings of the ROM to `config`
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(variant) = self.variant {
+            config.variant = variant;
+        }
+        if let Some(quirks) = self.quirks {
+            config.quirks = Some(quirks);
+        }
+        if let Some(ipf) = self.ipf {
+            config.ipf = ipf;
+        }
+        if let Some(palette) = &self.palette {
+            config.palette = palette.clone();
+        }
+    }
+
+    /// Return the keys of the keypad and what they do, like
+    /// `1 up, 4 down`
+    pub fn key_hints(&self) -> String {
+        self.keys
+            .iter()
+            .map(|(key, hint)| format!("{key:X} {hint}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn parse(value: &Value) -> Result<RomInfo, String> {
+        let Value::Object(fields) = value else {
+            return Err("expected an object".to_string());
+        };
+        let string = |name: &str| match fields.get(name) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(format!("{name}: expected a string")),
+        };
+
+        let mut info = RomInfo {
+            title: string("title")?.ok_or("title: missing")?,
+            author: string("author")?,
+            ..RomInfo::default()
+        };
+        for (name, value) in fields {
+            match name.as_str() {
+                "title" | "author" => {}
+                "platform" => info.variant = Some(parse(name, value)?),
+                "quirks" => info.quirks = Some(parse(name, value)?),
+                "palette" => info.palette = Some(parse(name, value)?),
+                "ipf" => {
+                    info.ipf = Some(
+                        value
+                            .as_u64()
+                            .filter(|ipf| *ipf > 0)
+                            .ok_or("ipf: expected a positive number")?
+                            as usize,
+                    )
+                }
+                "keys" => {
+                    let Value::Object(keys) = value else {
+                        return Err("keys: expected an object".to_string());
+                    };
+                    for (key, hint) in keys {
+                        let k = u8::from_str_radix(key, 16)
+                            .ok()
+                            .filter(|k| *k < 16)
+                            .ok_or_else(|| format!("keys: '{key}' is not a key of the keypad"))?;
+                        let hint = hint
+                            .as_str()
+                            .ok_or_else(|| format!("keys.{key}: expected a string"))?;
+                        info.keys.push((k, hint.to_string()));
+                    }
+                    info.keys.sort();
+                }
+                _ => return Err(format!("{name}: unknown field")),
+            }
+        }
+        Ok(info)
+    }
+}
+
+/// Parse the string `value` of the field `name`
+fn parse<T: FromStr<Err = String>>(name: &str, value: &Value) -> Result<T, String> {
+    let s = value
+        .as_str()
+        .ok_or_else(|| format!("{name}: expected a string"))?;
+    s.parse().map_err(|e| format!("{name}: {e}"))
+}
+
+/// ROMs of the database by the SHA-1 of their content
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Database {
+    roms: HashMap<String, RomInfo>,
+}
+
+impl Database {
+    /// Read the database at `path`, it is empty if the file doesn't exist
+    pub fn load(path: &Path) -> Result<Database, String> {
+        match fs::read_to_string(path) {
+            Ok(content) => {
+                Database::parse(&content).map_err(|e| format!("{}: {e}", path.display()))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Database::default()),
+            Err(e) => Err(format!("{}: {e}", path.display())),
+        }
+    }
+
+    /// Read the database at `path` or, without one, the default database
+    /// which is not used if it can't be read
+    pub fn open(path: Option<&Path>) -> Result<Database, String> {
+        match path {
+            Some(path) => Database::load(path),
+            None => Ok(
+                Database::load(Path::new(DATABASE_FILE)).unwrap_or_else(|e| {
+                    log::warn!("The ROM database is not used: {e}");
+                    Database::default()
+                }),
+            ),
+        }
+    }
+
+    /// Parse the content of a database file, errors start with the hash of
+    /// the ROM
+    pub fn parse(content: &str) -> Result<Database, String> {
+        let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        let Value::Object(entries) = value else {
+            return Err("expected an object of ROMs by SHA-1".to_string());
+        };
+
+        let mut roms = HashMap::new();
+        for (hash, value) in &entries {
+            if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("{hash}: expected the SHA-1 of a ROM"));
+            }
+            let info = RomInfo::parse(value).map_err(|e| format!("{hash}.{e}"))?;
+            roms.insert(hash.to_ascii_lowercase(), info);
+        }
+        Ok(Database { roms })
+    }
+
+    /// Return what the database knows about `rom`
+    pub fn get(&self, rom: &[u8]) -> Option<&RomInfo> {
+        self.roms.get(&sha1::hex_digest(rom))
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+}
@@ -0,0 +1,72 @@
+//! ## Disassembler
+//! Mnemonics follow [Cowgod's Chip-8 Technical Reference](http://devernay.free.fr/hacks/chip8/C8TECH10.HTM).
+//! Values are printed in hexadecimal and opcodes that are not instructions
+//! are printed as data (`DW 0xXXXX`).
+
+/// Return the mnemonic of `opcode`
+pub fn mnemonic(opcode: u16) -> String {
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+    let n = opcode & 0x000F;
+    let kk = opcode & 0x00FF;
+    let nnn = opcode & 0x0FFF;
+
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => "CLS".to_string(),
+            0x00EE => "RET".to_string(),
+            0x00C0..=0x00CF => format!("SCD {n:#x}"),
+            0x00FB => "SCR".to_string(),
+            0x00FC => "SCL".to_string(),
+            0x00FE => "LOW".to_string(),
+            0x00FF => "HIGH".to_string(),
+            _ => format!("SYS {nnn:#05x}"),
+        },
+        0x1000 => format!("JP {nnn:#05x}"),
+        0x2000 => format!("CALL {nnn:#05x}"),
+        0x3000 => format!("SE V{x:X}, {kk:#04x}"),
+        0x4000 => format!("SNE V{x:X}, {kk:#04x}"),
+        0x5000 if n == 0 => format!("SE V{x:X}, V{y:X}"),
+        0x6000 => format!("LD V{x:X}, {kk:#04x}"),
+        0x7000 => format!("ADD V{x:X}, {kk:#04x}"),
+        0x8000 => match n {
+            0x0 => format!("LD V{x:X}, V{y:X}"),
+            0x1 => format!("OR V{x:X}, V{y:X}"),
+            0x2 => format!("AND V{x:X}, V{y:X}"),
+            0x3 => format!("XOR V{x:X}, V{y:X}"),
+            0x4 => format!("ADD V{x:X}, V{y:X}"),
+            0x5 => format!("SUB V{x:X}, V{y:X}"),
+            0x6 => format!("SHR V{x:X}, V{y:X}"),
+            0x7 => format!("SUBN V{x:X}, V{y:X}"),
+            0xE => format!("SHL V{x:X}, V{y:X}"),
+            _ => data(opcode),
+        },
+        0x9000 if n == 0 => format!("SNE V{x:X}, V{y:X}"),
+        0xA000 => format!("LD I, {nnn:#05x}"),
+        0xB000 => format!("JP V0, {nnn:#05x}"),
+        0xC000 => format!("RND V{x:X}, {kk:#04x}"),
+        0xD000 => format!("DRW V{x:X}, V{y:X}, {n:#x}"),
+        0xE000 => match kk {
+            0x9E => format!("SKP V{x:X}"),
+            0xA1 => format!("SKNP V{x:X}"),
+            _ => data(opcode),
+        },
+        0xF000 => match kk {
+            0x07 => format!("LD V{x:X}, DT"),
+            0x0A => format!("LD V{x:X}, K"),
+            0x15 => format!("LD DT, V{x:X}"),
+            0x18 => format!("LD ST, V{x:X}"),
+            0x1E => format!("ADD I, V{x:X}"),
+            0x29 => format!("LD F, V{x:X}"),
+            0x33 => format!("LD B, V{x:X}"),
+            0x55 => format!("LD [I], V{x:X}"),
+            0x65 => format!("LD V{x:X}, [I]"),
+            _ => data(opcode),
+        },
+        _ => data(opcode),
+    }
+}
+
+fn data(opcode: u16) -> String {
+    format!("DW {opcode:#06x}")
+}
@@ -0,0 +1,188 @@
+//! ## Display effects
+//! Games erase and redraw their sprites with XOR, so they flicker when the
+//! screen is shown as it is at the end of each frame. These effects are
+//! computed on the history of the framebuffer, frame after frame:
+//! - `fade`: like the phosphor of a CRT, a pixel that is cleared fades out
+//!   over a few frames instead of disappearing,
+//! - `blend`: the last two frames are mixed, a pixel set in only one of them
+//!   is drawn at half its intensity.
+//!
+//! Overlays darken parts of each pixel of the window: the bottom half of the
+//! rows for scanlines, the last row and column for the grid. They are drawn
+//! in the image of the screen, where each pixel is a square of `scale` x
+//! `scale`.
+
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::image::is_set;
+use crate::palette::Palette;
+
+/// Number of frames a pixel takes to fade out by default
+pub const DEFAULT_FADE_FRAMES: usize = 4;
+/// Brightness of scanlines
+pub const SCANLINE_BRIGHTNESS: f32 = 0.6;
+/// Brightness of the lines of the grid
+pub const GRID_BRIGHTNESS: f32 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Persistence {
+    /// Show the framebuffer as it is
+    #[default]
+    Off,
+    Blend,
+    Fade,
+}
+
+impl FromStr for Persistence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Persistence::Off),
+            "blend" => Ok(Persistence::Blend),
+            "fade" => Ok(Persistence::Fade),
+            _ => Err(format!("unknown persistence '{s}' (off, blend or fade)")),
+        }
+    }
+}
+
+impl fmt::Display for Persistence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Persistence::Off => "off",
+            Persistence::Blend => "blend",
+            Persistence::Fade => "fade",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Intensity of the pixels of the screen, from 0 to 1
+pub struct Phosphor {
+    width: usize,
+    height: usize,
+    persistence: Persistence,
+    fade_frames: usize,
+    levels: Vec<f32>,
+    /// Pixels of the previous frame, for blending
+    previous: Vec<bool>,
+}
+
+impl Phosphor {
+    pub fn new(width: usize, height: usize, persistence: Persistence, fade_frames: usize) -> Self {
+        Phosphor {
+            width,
+            height,
+            persistence,
+            fade_frames: fade_frames.max(1),
+            levels: vec![0.0; width * height],
+            previous: vec![false; width * height],
+        }
+    }
+
+    pub fn resolution(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Update the intensities with the framebuffer of a new frame
+    pub fn update(&mut self, fb: &[u8]) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = y * self.width + x;
+                let set = is_set(fb, self.width, x, y);
+                let level = &mut self.levels[idx];
+
+                *level = match self.persistence {
+                    Persistence::Off => set as u8 as f32,
+                    Persistence::Blend => (set as u8 + self.previous[idx] as u8) as f32 / 2.0,
+                    Persistence::Fade if set => 1.0,
+                    Persistence::Fade => (*level - 1.0 / self.fade_frames as f32).max(0.0),
+                };
+                self.previous[idx] = set;
+            }
+        }
+    }
+
+    /// Return the intensity of the pixel at (x, y)
+    pub fn level(&self, x: usize, y: usize) -> f32 {
+        self.levels[y * self.width + x]
+    }
+
+    /// Return the colour of each pixel, row by row
+    pub fn colors(&self, palette: &Palette) -> Vec<[u8; 3]> {
+        let (fg, bg) = (palette.foreground(), palette.background());
+        self.levels.iter().map(|l| mix(bg, fg, *l)).collect()
+    }
+
+    /// Return the RGBA image of the screen, each pixel is a square of
+    /// `scale` x `scale` with the overlays
+    pub fn to_rgba(&self, palette: &Palette, scale: usize, overlays: &Overlays) -> Vec<u8> {
+        let colors = self.colors(palette);
+        let brightness: Vec<f32> = (0..scale * scale)
+            .map(|i| overlays.brightness(i % scale, i / scale, scale))
+            .collect();
+
+        let mut rgba = Vec::with_capacity(self.width * self.height * scale * scale * 4);
+        for y in 0..self.height * scale {
+            for x in 0..self.width * scale {
+                let color = colors[(y / scale) * self.width + x / scale];
+                let b = brightness[(y % scale) * scale + x % scale];
+                let color = mix([0; 3], color, b);
+                rgba.extend_from_slice(&[color[0], color[1], color[2], 255]);
+            }
+        }
+        rgba
+    }
+}
+
+/// Return the colour between `a` and `b`, `t` going from 0 (`a`) to 1 (`b`)
+pub fn mix(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
+    let t = t.clamp(0.0, 1.0);
+    let mut c = [0; 3];
+    for i in 0..3 {
+        c[i] = (a[i] as f32 + (b[i] as f32 - a[i] as f32) * t).round() as u8;
+    }
+    c
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Overlays {
+    pub scanlines: bool,
+    pub grid: bool,
+}
+
+impl Overlays {
+    /// Rows of a pixel of `scale` x `scale` darkened by scanlines, there are
+    /// none if pixels are too small
+    pub fn scanline_rows(&self, scale: usize) -> Range<usize> {
+        if self.scanlines && scale >= 2 {
+            scale / 2..scale
+        } else {
+            0..0
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.scanlines && !self.grid
+    }
+
+    /// Return true if the grid is drawn on pixels of `scale` x `scale`
+    pub fn has_grid(&self, scale: usize) -> bool {
+        self.grid && scale >= 3
+    }
+
+    /// Return the brightness of the point (x, y) of a pixel of `scale` x
+    /// `scale`
+    pub fn brightness(&self, x: usize, y: usize, scale: usize) -> f32 {
+        let mut brightness = 1.0;
+        if self.scanline_rows(scale).contains(&y) {
+            brightness *= SCANLINE_BRIGHTNESS;
+        }
+        if self.has_grid(scale) && (x == scale - 1 || y == scale - 1) {
+            brightness *= GRID_BRIGHTNESS;
+        }
+        brightness
+    }
+}
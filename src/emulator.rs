@@ -29,13 +29,19 @@
 //!
 //! ### Graphics and sound
 //! - Display is 64x32 pixels and monochrome
+//!     - SCHIP switches to 128x64 pixels with 00FF and back with 00FE, the
+//!       high resolution display is kept outside of the memory
+//!     - SCHIP scrolls the display down with 00CN, right with 00FB and left
+//!       with 00FC
 //! - Graphics are drawn using sprites
-//!     - sprites is 8 wide and 1->15 pixels height
+//!     - sprites is 8 wide and 1->15 pixels height, DXY0 draws a 16x16
+//!       sprite of SCHIP
 //!     - sprites are XOR'ed with corresponding screen pixels
 //! - A beeping sound is played when sound timer is nonzero.
 
+use crate::trace::Tracer;
 use log;
-use std::{fmt, fs::File, io::Read};
+use std::{collections::VecDeque, fmt, fs, io, str::FromStr};
 
 /// Chip8 has 4Ko of RAM
 const MEMSIZE: usize = 4096;
@@ -48,6 +54,24 @@ const FONTS_OFFSET: usize = 0x0;
 const _FONTS_WIDTH: usize = 8;
 const FONTS_HEIGHT: usize = 5;
 const FONTS_SIZE: usize = 80;
+const FONTS: [u8; FONTS_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
 /// Display offset
 const DISPLAY_OFFSET: usize = 0xF00;
 /// Display width in pixels
@@ -56,13 +80,21 @@ const DISPLAY_WIDTH: usize = 64;
 const DISPLAY_HEIGHT: usize = 32;
 /// Display size is 256 bytes
 const DISPLAY_SIZE: usize = (DISPLAY_WIDTH * DISPLAY_HEIGHT) / 8;
+/// The high resolution of SCHIP is 128x64 pixels, its 1024 bytes don't fit
+/// in memory so they are kept apart
+const HIRES_WIDTH: usize = 128;
+const HIRES_HEIGHT: usize = 64;
+const HIRES_SIZE: usize = (HIRES_WIDTH * HIRES_HEIGHT) / 8;
 /// 16 Data registers named V0 to VF
 const VREGS_SIZE: usize = 16;
 /// Opcode is 2 bytes
 const OPCODE_SIZE: usize = 2;
 /// Keyboard has 16 values from 0 to F
 const KEYBOARD_SIZE: usize = 16;
+/// Number of executed instructions kept in the history by default
+pub const HISTORY_SIZE: usize = 64;
 
+#[derive(Clone, Copy, PartialEq)]
 pub enum Chip8Error {
     UnknownOpcode(u16),
     UndefinedHexadecimal(u16),
@@ -71,17 +103,20 @@ pub enum Chip8Error {
     VregsOverflow,
     MemoryFull,
     WrongKey,
+    /// The ROM file can't be read
+    Io(io::ErrorKind),
 }
 
 impl fmt::Display for Chip8Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Chip8Error::UnknownOpcode(opcode) => write!(f, "Opcode <{opcode}> is unknown"),
+            Chip8Error::UnknownOpcode(opcode) => write!(f, "Opcode <{opcode:#06x}> is unknown"),
             Chip8Error::StackOverflow => write!(f, "Stack overflow detected"),
             Chip8Error::StackUnderflow => write!(f, "Stack underflow detected"),
             Chip8Error::VregsOverflow => write!(f, "Vregs overflow detected"),
             Chip8Error::MemoryFull => write!(f, "Memory is full"),
             Chip8Error::WrongKey => write!(f, "Key is not valid"),
+            Chip8Error::Io(kind) => write!(f, "Cannot read the ROM: {kind}"),
             Chip8Error::UndefinedHexadecimal(v) => {
                 write!(f, "Hexadecimal error: Expected a value under 16, got {v}")
             }
@@ -95,6 +130,67 @@ impl fmt::Debug for Chip8Error {
     }
 }
 
+/// Behaviours that differ between CHIP-8 interpreters. The default is the
+/// historical behaviour of this emulator.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Quirks {
+    /// 8xy6 and 8xyE shift Vy and store the result in Vx, instead of shifting
+    /// Vx in place
+    pub shift_vy: bool,
+    /// Fx55 and Fx65 leave I pointing after the last register
+    pub load_store_increment_i: bool,
+    /// Bxnn jumps to xnn + Vx instead of nnn + V0
+    pub jump_vx: bool,
+    /// 8xy1, 8xy2 and 8xy3 reset VF
+    pub vf_reset: bool,
+}
+
+impl Quirks {
+    /// Names of the presets accepted by [`Quirks::from_str`]
+    pub const PRESETS: [&'static str; 4] = ["default", "vip", "schip", "xochip"];
+
+    /// The original COSMAC VIP interpreter
+    pub const VIP: Quirks = Quirks {
+        shift_vy: true,
+        load_store_increment_i: true,
+        jump_vx: false,
+        vf_reset: true,
+    };
+
+    /// SUPER-CHIP 1.1
+    pub const SCHIP: Quirks = Quirks {
+        shift_vy: false,
+        load_store_increment_i: false,
+        jump_vx: true,
+        vf_reset: false,
+    };
+
+    /// XO-CHIP
+    pub const XOCHIP: Quirks = Quirks {
+        shift_vy: true,
+        load_store_increment_i: true,
+        jump_vx: false,
+        vf_reset: false,
+    };
+}
+
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Quirks::default()),
+            "vip" => Ok(Quirks::VIP),
+            "schip" => Ok(Quirks::SCHIP),
+            "xochip" => Ok(Quirks::XOCHIP),
+            _ => Err(format!(
+                "unknown quirks preset '{s}' ({})",
+                Quirks::PRESETS.join(", ")
+            )),
+        }
+    }
+}
+
 pub struct Chip8 {
     /// 4K memory
     mem: [u8; MEMSIZE],
@@ -112,6 +208,75 @@ pub struct Chip8 {
     sound_timer: u16,
     // Keyboard status, true means key is pressed
     keyboard: [bool; KEYBOARD_SIZE],
+    // Optional tracer that records each executed instruction
+    tracer: Option<Tracer>,
+    // State of the pseudo random generator when a seed is set. Otherwise
+    // random numbers come from the hardware.
+    rng: Option<u64>,
+    // Seed of the generator, it restarts from it on hard resets
+    seed: Option<u64>,
+    // ROM loaded at the entry point, kept for hard resets
+    rom: Vec<u8>,
+    quirks: Quirks,
+    // Display of the high resolution, set when it is enabled by 00FF
+    hires: Option<Box<[u8; HIRES_SIZE]>>,
+    // Last executed instructions, the oldest first
+    history: VecDeque<Executed>,
+    history_size: usize,
+}
+
+/// Registers that instructions can change, besides the program counter
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Registers {
+    pub v: [u8; VREGS_SIZE],
+    pub i: u16,
+    pub dt: u16,
+    pub st: u16,
+}
+
+/// An instruction of the history with the registers before and after it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Executed {
+    pub pc: usize,
+    pub opcode: u16,
+    pub before: Registers,
+    pub after: Registers,
+}
+
+impl Executed {
+    /// Return the registers changed by the instruction, like `V1=0x05`
+    pub fn effects(&self) -> Vec<String> {
+        let (before, after) = (&self.before, &self.after);
+        let mut effects: Vec<String> = (0..VREGS_SIZE)
+            .filter(|x| before.v[*x] != after.v[*x])
+            .map(|x| format!("V{x:X}={:#04x}", after.v[x]))
+            .collect();
+        if before.i != after.i {
+            effects.push(format!("I={:#06x}", after.i));
+        }
+        if before.dt != after.dt {
+            effects.push(format!("DT={}", after.dt));
+        }
+        if before.st != after.st {
+            effects.push(format!("ST={}", after.st));
+        }
+        effects
+    }
+}
+
+/// State of the machine saved by [`Chip8::snapshot`]: the memory, the
+/// display, the registers, the stack, the timers and the random generator
+#[derive(Clone)]
+pub struct Snapshot {
+    mem: Box<[u8; MEMSIZE]>,
+    hires: Option<Box<[u8; HIRES_SIZE]>>,
+    pc: usize,
+    sp: Vec<usize>,
+    vregs: [u8; VREGS_SIZE],
+    i: u16,
+    delay_timer: u16,
+    sound_timer: u16,
+    rng: Option<u64>,
 }
 
 impl Default for Chip8 {
@@ -121,6 +286,18 @@ impl Default for Chip8 {
 }
 
 impl Chip8 {
+    /// Return a builder of a Chip8 that runs `program`. Opcodes are loaded
+    /// at the entry point. It is useful for testing instructions without
+    /// ROM files.
+    pub fn with_program(program: &[u16]) -> Chip8Builder {
+        let rom: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+        let mut chip = Chip8::new();
+        chip.load_bytes(&rom)
+            .expect("program doesn't fit in memory");
+
+        Chip8Builder { chip }
+    }
+
     pub fn new() -> Self {
         Chip8 {
             mem: [0; MEMSIZE],
@@ -131,67 +308,204 @@ impl Chip8 {
             delay_timer: 0,
             sound_timer: 0,
             keyboard: [false; KEYBOARD_SIZE],
+            tracer: None,
+            rng: None,
+            seed: None,
+            rom: vec![],
+            quirks: Quirks::default(),
+            hires: None,
+            history: VecDeque::with_capacity(HISTORY_SIZE),
+            history_size: HISTORY_SIZE,
         }
     }
 
-    /// Loads in memory the `rom` passed as a parameter.
-    /// The `rom` must be a file that contains a valid ROM.
-    /// There is no check done when loading it.
+    /// Loads in memory the ROM in the file `from`.
+    /// Returns [`Chip8Error::Io`] if the file can't be read and
+    /// [`Chip8Error::MemoryFull`] if the ROM doesn't fit (see
+    /// [`load_bytes`](Chip8::load_bytes)).
     pub fn load(&mut self, from: &str) -> Result<(), Chip8Error> {
-        // We can read byte per byte
-        let mut byte: [u8; 1] = [0];
+        let rom = fs::read(from).map_err(|e| Chip8Error::Io(e.kind()))?;
+        self.load_bytes(&rom)
+    }
+
+    /// Loads in memory the `rom` passed as bytes.
+    /// Returns [`Chip8Error::MemoryFull`] if it is larger than the
+    /// 0xEA0 - 0x200 bytes between the entry point and the call stack.
+    pub fn load_bytes(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        // The program must end before the call stack
+        if ENTRY_POINT + rom.len() > 0x0EA0 {
+            return Err(Chip8Error::MemoryFull);
+        }
+        self.rom = rom.to_vec();
+        self.hard_reset();
+        Ok(())
+    }
 
-        // We don't want to change the PC so don't use self.pc to load
-        // the program
-        let mut pc = self.pc;
-        let mut f = File::open(from).unwrap();
+    /// Copy the ROM at the entry point again, to run a new version of it
+    /// from a snapshot taken with an older one
+    pub fn reload_rom(&mut self) {
+        self.mem[ENTRY_POINT..ENTRY_POINT + self.rom.len()].copy_from_slice(&self.rom);
+    }
 
-        while let Ok(()) = f.read_exact(&mut byte) {
-            if pc >= 0x0EA0 {
-                return Err(Chip8Error::MemoryFull);
-            }
-            self.mem[pc] = byte[0];
-            pc += 1;
+    /// Save the state of the machine
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            mem: Box::new(self.mem),
+            hires: self.hires.clone(),
+            pc: self.pc,
+            sp: self.sp.clone(),
+            vregs: self.vregs,
+            i: self.i,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            rng: self.rng,
         }
+    }
 
-        // Load the fonts at FONTS_OFFSET
-        self.mem[FONTS_OFFSET..(FONTS_OFFSET + FONTS_SIZE)].copy_from_slice(&[
-            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-            0x20, 0x60, 0x20, 0x20, 0x70, // 1
-            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-        ]);
-
-        // Write 0xFF in display so we will be able to check that clean Display
-        // is working.
-        self.mem[DISPLAY_OFFSET..(DISPLAY_OFFSET + DISPLAY_SIZE)]
-            .copy_from_slice(&[0xFF; DISPLAY_SIZE]);
+    /// Restore a state saved by [`Chip8::snapshot`]. The ROM, the quirks and
+    /// the keyboard are kept.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.mem = *snapshot.mem;
+        self.hires = snapshot.hires.clone();
+        self.pc = snapshot.pc;
+        self.sp = snapshot.sp.clone();
+        self.vregs = snapshot.vregs;
+        self.i = snapshot.i;
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.rng = snapshot.rng;
+        self.history.clear();
+    }
 
-        Ok(())
+    /// Return the ROM given to [`Chip8::load_bytes`]
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    /// Reset the registers, the stack, the timers, the keyboard and the
+    /// display in low resolution, and restart at the entry point. The rest
+    /// of the memory is kept, with the changes made by the program.
+    pub fn reset(&mut self) {
+        self.pc = ENTRY_POINT;
+        self.sp.clear();
+        self.vregs = [0; VREGS_SIZE];
+        self.i = 0;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.keyboard = [false; KEYBOARD_SIZE];
+        self.history.clear();
+        // The display starts cleared, all pixels are off
+        self.hires = None;
+        self.mem[DISPLAY_OFFSET..(DISPLAY_OFFSET + DISPLAY_SIZE)].fill(0);
+    }
+
+    /// Reset the machine like when it is switched on: the memory is cleared
+    /// then the fonts and the original ROM are loaded again. The random
+    /// generator restarts from its seed.
+    pub fn hard_reset(&mut self) {
+        self.reset();
+        self.mem.fill(0);
+        self.mem[FONTS_OFFSET..(FONTS_OFFSET + FONTS_SIZE)].copy_from_slice(&FONTS);
+        self.reload_rom();
+        self.rng = self.seed;
     }
 
-    /// Return a reference to memory related to display
+    /// Return a reference to memory related to display. In high resolution
+    /// it is the display kept apart from the memory.
     pub fn get_framebuffer(&self) -> &[u8] {
-        &self.mem[DISPLAY_OFFSET..(DISPLAY_OFFSET + DISPLAY_SIZE)]
+        match &self.hires {
+            Some(fb) => &fb[..],
+            None => &self.mem[DISPLAY_OFFSET..(DISPLAY_OFFSET + DISPLAY_SIZE)],
+        }
+    }
+
+    fn framebuffer_mut(&mut self) -> &mut [u8] {
+        match &mut self.hires {
+            Some(fb) => &mut fb[..],
+            None => &mut self.mem[DISPLAY_OFFSET..(DISPLAY_OFFSET + DISPLAY_SIZE)],
+        }
+    }
+
+    /// Return the width and the height of the display in pixels
+    pub fn resolution(&self) -> (usize, usize) {
+        match self.hires {
+            Some(_) => (HIRES_WIDTH, HIRES_HEIGHT),
+            None => (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+        }
+    }
+
+    /// Move the pixels of the display by `dx` and `dy`. Pixels that leave
+    /// the display are lost and the ones that enter it are off.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = self.resolution();
+        let old = self.get_copy_of_framebuffer();
+        let fb = self.framebuffer_mut();
+        fb.fill(0);
+
+        for y in 0..height {
+            for x in 0..width {
+                let (from_x, from_y) = (x as isize - dx, y as isize - dy);
+                if from_x < 0 || from_y < 0 || from_x >= width as isize || from_y >= height as isize
+                {
+                    continue;
+                }
+                let from = from_y as usize * width + from_x as usize;
+                if old[from / 8] & (0x80 >> (from % 8)) != 0 {
+                    fb[(y * width + x) / 8] |= 0x80 >> (x % 8);
+                }
+            }
+        }
     }
 
     /// Return a copy of memory related to display
     pub fn get_copy_of_framebuffer(&self) -> Vec<u8> {
-        let mut buf = vec![0; DISPLAY_SIZE];
-        buf.copy_from_slice(&self.mem[DISPLAY_OFFSET..(DISPLAY_OFFSET + DISPLAY_SIZE)]);
-        buf
+        self.get_framebuffer().to_vec()
+    }
+
+    /// Return the program counter
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Return the data registers V0 to VF
+    pub fn vregs(&self) -> &[u8] {
+        &self.vregs
+    }
+
+    /// Return the data register Vx
+    pub fn vreg(&self, x: usize) -> u8 {
+        self.vregs[x]
+    }
+
+    /// Return the address register I
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    /// Return the return addresses pushed on the stack
+    pub fn stack(&self) -> &[usize] {
+        &self.sp
+    }
+
+    /// Return the delay timer
+    pub fn delay_timer(&self) -> u16 {
+        self.delay_timer
+    }
+
+    /// Return the sound timer
+    pub fn sound_timer(&self) -> u16 {
+        self.sound_timer
+    }
+
+    /// Return a reference to the whole memory
+    pub fn memory(&self) -> &[u8] {
+        &self.mem
+    }
+
+    /// Return a mutable reference to the whole memory. It is used by debuggers
+    /// to patch the program.
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.mem
     }
 
     /// Emulate the instruction at program counter.
@@ -200,27 +514,15 @@ impl Chip8 {
     pub fn emulate_insn(&mut self) -> Result<(), Chip8Error> {
         let opcode: u16 = ((self.mem[self.pc] as u16) << 8) | (self.mem[self.pc + 1] as u16);
 
-        log::debug!("pc = {:#06x}, opcode = {}", self.pc, opcode);
+        log::debug!("pc = {:#06x}, opcode = {:#06x}", self.pc, opcode);
 
         self.pc += OPCODE_SIZE;
 
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
-        }
-
-        if self.sound_timer > 0 {
-            // TODO: emit a sound if not equal to 0
-            self.sound_timer -= 1;
-        }
-
         match opcode & 0xF000 {
             0x0000 => {
                 match opcode {
                     // CLS: clear screen
-                    0x00E0 => {
-                        self.mem[DISPLAY_OFFSET..(DISPLAY_OFFSET + DISPLAY_SIZE)]
-                            .copy_from_slice(&[0; DISPLAY_SIZE]);
-                    }
+                    0x00E0 => self.framebuffer_mut().fill(0),
                     // RET: return from subroutine
                     0x00EE => {
                         self.pc = match self.sp.pop() {
@@ -228,9 +530,22 @@ impl Chip8 {
                             Some(r) => r,
                         };
                     }
+                    // SCD nibble: scroll down n lines
+                    0x00C0..=0x00CF => self.scroll(0, (opcode & 0xF) as isize),
+                    // SCR: scroll right 4 pixels
+                    0x00FB => self.scroll(4, 0),
+                    // SCL: scroll left 4 pixels
+                    0x00FC => self.scroll(-4, 0),
+                    // LOW: SCHIP low resolution, the display is cleared
+                    0x00FE => {
+                        self.hires = None;
+                        self.framebuffer_mut().fill(0);
+                    }
+                    // HIGH: SCHIP high resolution, the display is cleared
+                    0x00FF => self.hires = Some(Box::new([0; HIRES_SIZE])),
                     // SYS Addr
                     _ => {
-                        log::info!("{opcode} is ignored by modern interpreters");
+                        log::info!("{opcode:#06x} is ignored by modern interpreters");
                     }
                 }
             }
@@ -304,14 +619,23 @@ impl Chip8 {
                     // OR Vx, Vy
                     0x1 => {
                         self.vregs[x] |= self.vregs[y];
+                        if self.quirks.vf_reset {
+                            self.vregs[0xF] = 0;
+                        }
                     }
                     // AND Vx, Vy
                     0x2 => {
                         self.vregs[x] &= self.vregs[y];
+                        if self.quirks.vf_reset {
+                            self.vregs[0xF] = 0;
+                        }
                     }
                     // XOR Vx, Vy
                     0x3 => {
                         self.vregs[x] ^= self.vregs[y];
+                        if self.quirks.vf_reset {
+                            self.vregs[0xF] = 0;
+                        }
                     }
                     // ADD Vx, Vy
                     0x4 => {
@@ -327,8 +651,13 @@ impl Chip8 {
                     }
                     // SHR Vx {, Vy}
                     0x6 => {
-                        self.vregs[0xF] = if self.vregs[x] & 0x1 == 0x1 { 1 } else { 0 };
-                        self.vregs[x] /= 2;
+                        let v = if self.quirks.shift_vy {
+                            self.vregs[y]
+                        } else {
+                            self.vregs[x]
+                        };
+                        self.vregs[0xF] = if v & 0x1 == 0x1 { 1 } else { 0 };
+                        self.vregs[x] = v / 2;
                     }
                     // SUBN Vx, Vy
                     0x7 => {
@@ -337,8 +666,13 @@ impl Chip8 {
                     }
                     // SHL Vx {, Vy}
                     0xE => {
-                        self.vregs[0xF] = if self.vregs[x] & 0x80 == 0x80 { 1 } else { 0 };
-                        self.vregs[x] = (self.vregs[x] as usize * 2) as u8;
+                        let v = if self.quirks.shift_vy {
+                            self.vregs[y]
+                        } else {
+                            self.vregs[x]
+                        };
+                        self.vregs[0xF] = if v & 0x80 == 0x80 { 1 } else { 0 };
+                        self.vregs[x] = (v as usize * 2) as u8;
                     }
                     _ => return Err(Chip8Error::UnknownOpcode(opcode)),
                 }
@@ -361,36 +695,48 @@ impl Chip8 {
             // LD I, addr
             0xA000 => self.i = opcode & 0xFFF,
             // JP V0, addr
-            0xB000 => self.pc = (opcode & 0xFFF) as usize + self.vregs[0] as usize,
+            0xB000 => {
+                let v = if self.quirks.jump_vx {
+                    self.vregs[((opcode & 0x0F00) >> 8) as usize]
+                } else {
+                    self.vregs[0]
+                };
+                self.pc = (opcode & 0xFFF) as usize + v as usize;
+            }
             // Vx = rand() & NN
             0xC000 => {
                 let x = ((opcode & 0x0F00) >> 8) as usize;
                 let kk = (opcode & 0xFF) as u8;
 
-                let rand = unsafe {
-                    let mut r = 0_u16;
-                    if core::arch::x86_64::_rdrand16_step(&mut r) == 0 {
-                        log::warn!("failed to generate random number");
-                    };
+                let rand = match self.rng.as_mut() {
+                    Some(state) => splitmix64(state) as u8,
+                    None => unsafe {
+                        let mut r = 0_u16;
+                        if core::arch::x86_64::_rdrand16_step(&mut r) == 0 {
+                            log::warn!("failed to generate random number");
+                        };
 
-                    r as u8
+                        r as u8
+                    },
                 };
                 self.vregs[x] = rand & kk;
             }
             // DRAW Vx, Vy, nibble
             0xD000 => {
-                // Draw a sprite 8xN at coordinate (VX, VY)
-                // VX and VY are in pixels
+                // Draw a sprite 8xN at coordinate (VX, VY), or a 16x16 one
+                // when N is 0. VX and VY are in pixels
                 let x = ((opcode & 0x0F00) >> 8) as usize;
                 let y = ((opcode & 0x00F0) >> 4) as usize;
                 let n = (opcode & 0xF) as usize;
+                // Bytes per row and rows of the sprite
+                let (cols, rows) = if n == 0 { (2, 16) } else { (1, n) };
 
                 let vx = self.vregs[x] as usize;
                 let vy = self.vregs[y] as usize;
 
-                log::debug!("Draw a 8x{n} sprite at ({vx}, {vy})");
+                log::debug!("Draw a {}x{rows} sprite at ({vx}, {vy})", cols * 8);
 
-                let sprite = &self.mem[self.i as usize..(self.i as usize + n)];
+                let sprite = &self.mem[self.i as usize..(self.i as usize + cols * rows)];
                 log::debug!("Sprite is {sprite:?}");
 
                 self.vregs[0xF] = 0; // Will be set if a pixel is set from set to unset
@@ -398,52 +744,58 @@ impl Chip8 {
                 // We need to use a copy of the framebuffer because sprite has an immutable
                 // borrow on self.mem.
                 let mut fb_copy = self.get_copy_of_framebuffer();
+                let (width, height) = self.resolution();
 
-                for (idx, pixels) in sprite.iter().enumerate() {
-                    log::debug!("  idx {idx}, pixels {pixels}");
-                    // We need to find in which coordinate the pixel falls. Pixel 0-7 are in first
-                    // byte, 8-15 in the second and so on.
-                    let start_idx = vx / 8;
-                    let end_idx = (vx + 7) / 8;
-                    let offset = vx % 8;
-
-                    let start_byte = start_idx + ((vy + idx) * 8);
-                    let end_byte = end_idx + ((vy + idx) * 8);
-
-                    if end_byte > 255 {
-                        // Skip if index are wrong
-                        log::warn!("Cannot draw at ({vx}, {vy}) on chip8 that is 64x32");
-                    } else if offset == 0 {
-                        // It it's aligned it easy
-                        let before = fb_copy[start_byte];
-                        fb_copy[start_byte] ^= pixels;
-                        let after = fb_copy[start_byte];
-
-                        if before & after != before {
-                            self.vregs[0xF] = 1;
-                        }
-                    } else {
-                        // It is not aligned so we need to shift pixels at the right place.
-                        let before = fb_copy[start_byte];
-                        fb_copy[start_byte] ^= pixels >> offset;
-                        let after = fb_copy[start_byte];
-
-                        if before & after != before {
-                            self.vregs[0xF] = 1;
-                        }
+                for (idx, row) in sprite.chunks(cols).enumerate() {
+                    for (c, pixels) in row.iter().enumerate() {
+                        // The right half of 16x16 sprites is 8 pixels further
+                        let vx = vx + c * 8;
+                        log::debug!("  idx {idx}, pixels {pixels}");
+                        // We need to find in which coordinate the pixel falls. Pixel 0-7
+                        // are in first byte, 8-15 in the second and so on.
+                        let start_idx = vx / 8;
+                        let end_idx = vx.div_ceil(8);
+                        let offset = vx % 8;
+
+                        let start_byte = start_idx + ((vy + idx) * width / 8);
+                        let end_byte = end_idx + ((vy + idx) * width / 8);
+
+                        if end_byte >= fb_copy.len() {
+                            // Skip if index are wrong
+                            log::warn!("Cannot draw at ({vx}, {vy}) on a {width}x{height} display");
+                        } else if offset == 0 {
+                            // It it's aligned it easy
+                            let before = fb_copy[start_byte];
+                            fb_copy[start_byte] ^= pixels;
+                            let after = fb_copy[start_byte];
+
+                            if before & after != before {
+                                self.vregs[0xF] = 1;
+                            }
+                        } else {
+                            // It is not aligned so we need to shift pixels at the right
+                            // place.
+                            let before = fb_copy[start_byte];
+                            fb_copy[start_byte] ^= pixels >> offset;
+                            let after = fb_copy[start_byte];
 
-                        let before = fb_copy[end_byte];
-                        fb_copy[end_byte] ^= pixels << (8 - offset);
-                        let after = fb_copy[end_byte];
+                            if before & after != before {
+                                self.vregs[0xF] = 1;
+                            }
 
-                        if before & after != before {
-                            self.vregs[0xF] = 1;
+                            let before = fb_copy[end_byte];
+                            fb_copy[end_byte] ^= pixels << (8 - offset);
+                            let after = fb_copy[end_byte];
+
+                            if before & after != before {
+                                self.vregs[0xF] = 1;
+                            }
                         }
                     }
                 }
 
                 // Update the real framebuffer
-                self.mem[DISPLAY_OFFSET..(DISPLAY_OFFSET + DISPLAY_SIZE)].copy_from_slice(&fb_copy);
+                self.framebuffer_mut().copy_from_slice(&fb_copy);
             }
             0xE000 => {
                 match opcode & 0xFF {
@@ -478,7 +830,11 @@ impl Chip8 {
                     }
                     // LD Vx, k
                     0x0A => {
-                        todo!("Wait for a key press");
+                        // Execute the instruction again until a key is pressed
+                        match self.keyboard.iter().position(|pressed| *pressed) {
+                            Some(key) => self.vregs[x] = key as u8,
+                            None => self.pc -= OPCODE_SIZE,
+                        }
                     }
                     // LD DT, Vx
                     0x15 => {
@@ -517,6 +873,9 @@ impl Chip8 {
                         for i in 0..=x {
                             self.mem[self.i as usize + i] = self.vregs[i];
                         }
+                        if self.quirks.load_store_increment_i {
+                            self.i += x as u16 + 1;
+                        }
                     }
                     // LD Vx, [I]
                     0x65 => {
@@ -524,6 +883,9 @@ impl Chip8 {
                         for x in 0..=x {
                             self.vregs[x] = self.mem[self.i as usize + x];
                         }
+                        if self.quirks.load_store_increment_i {
+                            self.i += x as u16 + 1;
+                        }
                     }
                     _ => return Err(Chip8Error::UnknownOpcode(opcode)),
                 }
@@ -534,8 +896,116 @@ impl Chip8 {
         Ok(())
     }
 
+    /// Decrement the delay and the sound timers, it must be called at 60 Hz
+    /// whatever the number of instructions executed
+    pub fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        // The frontend beeps while the sound timer is not 0
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    /// Execute the `ipf` instructions of a frame then decrement the timers
+    pub fn run_frame(&mut self, ipf: usize) -> Result<(), Chip8Error> {
+        self.run(ipf)?;
+        self.tick_timers();
+        Ok(())
+    }
+
+    /// Execute `steps` instructions
+    pub fn run(&mut self, steps: usize) -> Result<(), Chip8Error> {
+        for _ in 0..steps {
+            self.step()?;
+        }
+        Ok(())
+    }
+
     pub fn step(&mut self) -> Result<(), Chip8Error> {
-        self.emulate_insn()
+        // The tracer is taken out because it needs to borrow self
+        if let Some(mut tracer) = self.tracer.take() {
+            match tracer.record(self) {
+                Ok(()) => self.tracer = Some(tracer),
+                Err(e) => log::error!("Tracing is stopped: {e}"),
+            }
+        }
+
+        let (pc, opcode, before) = (self.pc, self.opcode_at(self.pc), self.registers());
+        let result = self.emulate_insn();
+
+        if self.history_size > 0 {
+            if self.history.len() >= self.history_size {
+                self.history.pop_front();
+            }
+            self.history.push_back(Executed {
+                pc,
+                opcode,
+                before,
+                after: self.registers(),
+            });
+        }
+        result
+    }
+
+    /// Return the registers that instructions can change
+    pub fn registers(&self) -> Registers {
+        Registers {
+            v: self.vregs,
+            i: self.i,
+            dt: self.delay_timer,
+            st: self.sound_timer,
+        }
+    }
+
+    /// Keep the last `size` executed instructions in the history, 0 disables
+    /// it
+    pub fn set_history_size(&mut self, size: usize) {
+        self.history_size = size;
+        while self.history.len() > size {
+            self.history.pop_front();
+        }
+    }
+
+    /// Return the last executed instructions, the oldest first. The last one
+    /// is the one that failed after an error.
+    pub fn history(&self) -> &VecDeque<Executed> {
+        &self.history
+    }
+
+    /// Return the opcode at `addr`, 0 outside of the memory
+    pub fn opcode_at(&self, addr: usize) -> u16 {
+        match self.mem.get(addr..addr + 2) {
+            Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]),
+            None => 0,
+        }
+    }
+
+    /// Use a pseudo random generator initialized with `seed` instead of the
+    /// hardware one, so runs can be reproduced.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        self.rng = Some(seed);
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Set the tracer that records each instruction executed by step().
+    /// Passing None stops the tracing.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        if let Some(mut old) = std::mem::replace(&mut self.tracer, tracer) {
+            if let Err(e) = old.flush() {
+                log::error!("Failed to flush the trace: {e}");
+            }
+        }
+    }
+
+    /// Remove the tracer without flushing it, to give it to another machine
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
     }
 
     /// Dumps the content of all memory on stdin.
@@ -549,6 +1019,11 @@ impl Chip8 {
         println!();
     }
 
+    /// Return the status of the keyboard, true means key is pressed
+    pub fn keyboard(&self) -> &[bool] {
+        &self.keyboard
+    }
+
     pub fn reset_keyboard(&mut self) {
         self.keyboard = [false; KEYBOARD_SIZE];
     }
@@ -560,3 +1035,69 @@ impl Chip8 {
         }
     }
 }
+
+/// Presets the state of a [`Chip8`] created by [`Chip8::with_program`]
+pub struct Chip8Builder {
+    chip: Chip8,
+}
+
+impl Chip8Builder {
+    /// Set Vx to `value`
+    pub fn vreg(mut self, x: usize, value: u8) -> Self {
+        self.chip.vregs[x] = value;
+        self
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.chip.quirks = quirks;
+        self
+    }
+
+    /// Set the address register I
+    pub fn i(mut self, addr: u16) -> Self {
+        self.chip.i = addr;
+        self
+    }
+
+    /// Copy `bytes` in memory starting at `addr`
+    pub fn memory(mut self, addr: usize, bytes: &[u8]) -> Self {
+        self.chip.mem[addr..addr + bytes.len()].copy_from_slice(bytes);
+        self
+    }
+
+    /// Press `key`
+    pub fn key(mut self, key: usize) -> Self {
+        self.chip.keyboard[key] = true;
+        self
+    }
+
+    pub fn delay_timer(mut self, value: u16) -> Self {
+        self.chip.delay_timer = value;
+        self
+    }
+
+    pub fn sound_timer(mut self, value: u16) -> Self {
+        self.chip.sound_timer = value;
+        self
+    }
+
+    /// Use a pseudo random generator, see [`Chip8::set_seed`]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.chip.set_seed(seed);
+        self
+    }
+
+    pub fn build(self) -> Chip8 {
+        self.chip
+    }
+}
+
+/// Return the next number of the [SplitMix64](https://prng.di.unimi.it/splitmix64.c)
+/// generator
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,512 @@
+//! ## GDB remote serial protocol stub
+//! ### Links
+//! - [Remote protocol](https://sourceware.org/gdb/current/onlinedocs/gdb.html/Remote-Protocol.html)
+//! - [Target descriptions](https://sourceware.org/gdb/current/onlinedocs/gdb.html/Target-Descriptions.html)
+//!
+//! The stub listens on a local TCP port and is polled by the frontend once
+//! per frame, so the window keeps being refreshed while the machine is halted.
+//!
+//! ### Registers
+//! Registers are numbered as follow and multi-bytes registers are sent in
+//! little endian:
+//! - 0 -> 15: V0 to VF (8 bits)
+//! - 16: I (16 bits)
+//! - 17: PC (16 bits)
+//! - 18: SP, the depth of the stack (8 bits)
+//! - 19: DT, the delay timer (8 bits)
+//! - 20: ST, the sound timer (8 bits)
+//!
+//! ### Supported packets
+//! - `?`, `g`, `p`, `m`, `M`, `Z0`, `z0`, `s`, `c`, `k`, `D`
+//! - `qSupported`, `qXfer:features:read`, `QStartNoAckMode` and the few
+//!   thread queries gdb sends when it connects.
+//! - `qRcmd` for the monitor commands `reset` and `reset hard`, see
+//!   [`Chip8::reset`] and [`Chip8::hard_reset`].
+//! - `Ctrl-C` (0x03) interrupts a running machine.
+
+use std::collections::BTreeSet;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::emulator::Chip8;
+
+/// Number of registers described in the target description
+const REGS_COUNT: usize = 21;
+/// Maximum size of a packet we accept, it is advertised in qSupported
+const PACKET_SIZE: usize = 0x4000;
+/// Signal reported when the machine stops on a breakpoint or after a step
+const SIGTRAP: u8 = 5;
+/// Signal reported when the machine is interrupted by the client
+const SIGINT: u8 = 2;
+/// Signal reported when the emulation of an instruction failed
+const SIGILL: u8 = 4;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+/// What the machine is doing from the client point of view
+#[derive(PartialEq)]
+enum State {
+    /// Waiting for a client or for a command of the client
+    Halted,
+    /// Executing instructions until a breakpoint or an interrupt
+    Running,
+    /// No client is attached anymore, the machine runs freely
+    Detached,
+}
+
+pub struct GdbStub {
+    listener: TcpListener,
+    conn: Option<TcpStream>,
+    /// Bytes received from the client that are not yet processed
+    input: Vec<u8>,
+    /// Addresses of software breakpoints
+    breakpoints: BTreeSet<usize>,
+    state: State,
+    /// Set when the client requested QStartNoAckMode
+    no_ack: bool,
+    /// Set when a stop was reported, so resuming doesn't stop again on the
+    /// breakpoint at the PC
+    resuming: bool,
+}
+
+impl GdbStub {
+    /// Listen for a client on localhost. The machine stays halted until a
+    /// client is attached.
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        log::info!("Waiting for a gdb client on 127.0.0.1:{port}");
+
+        Ok(GdbStub {
+            listener,
+            conn: None,
+            input: vec![],
+            breakpoints: BTreeSet::new(),
+            state: State::Halted,
+            no_ack: false,
+            resuming: false,
+        })
+    }
+
+    /// Return the port the stub listens on, useful when it is bound to
+    /// port 0
+    pub fn port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// Return true if the machine is stopped by the debugger
+    pub fn is_halted(&self) -> bool {
+        self.state == State::Halted
+    }
+
+    /// Process the packets sent by the client and, if the machine is running,
+    /// execute up to `budget` instructions then decrement the timers, as in a
+    /// frame. It never blocks and returns true if the frame was run.
+    pub fn poll(&mut self, chip: &mut Chip8, budget: usize) -> bool {
+        match self.try_poll(chip, budget) {
+            Ok(ran) => ran,
+            Err(e) => {
+                log::error!("gdb connection lost: {e}");
+                self.disconnect();
+                false
+            }
+        }
+    }
+
+    fn try_poll(&mut self, chip: &mut Chip8, budget: usize) -> io::Result<bool> {
+        if self.conn.is_none() {
+            self.accept()?;
+        }
+
+        self.receive()?;
+
+        while let Some(packet) = self.next_packet()? {
+            self.handle(chip, &packet)?;
+        }
+
+        match self.state {
+            State::Halted => return Ok(false),
+            State::Detached => {
+                for _ in 0..budget {
+                    if let Err(e) = chip.step() {
+                        log::error!("{e}");
+                        self.state = State::Halted;
+                        break;
+                    }
+                }
+                chip.tick_timers();
+            }
+            State::Running => {
+                // Breakpoints are checked before each step, except the first
+                // one after a stop so we don't stop again where we resume.
+                for _ in 0..budget {
+                    if !self.resuming && self.breakpoints.contains(&chip.pc()) {
+                        self.stop(SIGTRAP)?;
+                        break;
+                    }
+                    self.resuming = false;
+                    if let Err(e) = chip.step() {
+                        log::error!("{e}");
+                        self.stop(SIGILL)?;
+                        break;
+                    }
+                }
+                chip.tick_timers();
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn accept(&mut self) -> io::Result<()> {
+        match self.listener.accept() {
+            Ok((stream, addr)) => {
+                log::info!("gdb client connected from {addr}");
+                stream.set_nonblocking(true)?;
+                stream.set_nodelay(true)?;
+                self.conn = Some(stream);
+                self.input.clear();
+                self.no_ack = false;
+                self.resuming = false;
+                self.state = State::Halted;
+                Ok(())
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.conn = None;
+        self.input.clear();
+        self.breakpoints.clear();
+        if self.state != State::Halted {
+            self.state = State::Detached;
+        }
+    }
+
+    /// Read everything that is available on the socket
+    fn receive(&mut self) -> io::Result<()> {
+        let Some(conn) = self.conn.as_mut() else {
+            return Ok(());
+        };
+
+        let mut buf = [0_u8; 1024];
+        loop {
+            match conn.read(&mut buf) {
+                Ok(0) => {
+                    log::info!("gdb client disconnected");
+                    self.disconnect();
+                    return Ok(());
+                }
+                Ok(n) => self.input.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Extract the next complete packet from the input. Acks are dropped and
+    /// an interrupt request stops the machine.
+    fn next_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.input.first() {
+                None => return Ok(None),
+                Some(b'$') => break,
+                Some(0x03) => {
+                    self.input.remove(0);
+                    if self.state == State::Running {
+                        self.stop(SIGINT)?;
+                    }
+                }
+                // '+', '-' and garbage between packets
+                Some(_) => {
+                    self.input.remove(0);
+                }
+            }
+        }
+
+        let Some(end) = self.input.iter().position(|&b| b == b'#') else {
+            if self.input.len() > PACKET_SIZE {
+                self.input.clear();
+            }
+            return Ok(None);
+        };
+
+        // We need the two hex digits of the checksum
+        if self.input.len() < end + 3 {
+            return Ok(None);
+        }
+
+        let raw: Vec<u8> = self.input.drain(..end + 3).collect();
+        let data = &raw[1..end];
+        let expected = std::str::from_utf8(&raw[end + 1..])
+            .ok()
+            .and_then(|s| u8::from_str_radix(s, 16).ok());
+
+        if !self.no_ack {
+            let ack: &[u8] = if expected == Some(checksum(data)) {
+                b"+"
+            } else {
+                b"-"
+            };
+            self.write_all(ack)?;
+            if ack == b"-" {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(String::from_utf8_lossy(data).into_owned()))
+    }
+
+    fn handle(&mut self, chip: &mut Chip8, packet: &str) -> io::Result<()> {
+        log::debug!("gdb <- {packet}");
+
+        if !packet.is_char_boundary(1) {
+            return self.send("");
+        }
+        let (cmd, args) = packet.split_at(1);
+
+        match cmd {
+            "?" => self.send(&format!("S{SIGTRAP:02x}")),
+            "g" => {
+                let regs: String = (0..REGS_COUNT)
+                    .map(|n| read_register(chip, n).unwrap())
+                    .collect();
+                self.send(&regs)
+            }
+            "p" => match usize::from_str_radix(args, 16)
+                .ok()
+                .and_then(|n| read_register(chip, n))
+            {
+                Some(reg) => self.send(&reg),
+                None => self.send("E01"),
+            },
+            "m" => match parse_addr_len(args) {
+                Some((addr, len)) => match in_memory(chip, addr, len) {
+                    Some(end) => self.send(&to_hex(&chip.memory()[addr..end])),
+                    None => self.send("E01"),
+                },
+                None => self.send("E01"),
+            },
+            "M" => {
+                let written = args.split_once(':').and_then(|(range, data)| {
+                    let (addr, len) = parse_addr_len(range)?;
+                    let bytes = from_hex(data)?;
+                    let end = in_memory(chip, addr, len).filter(|_| bytes.len() == len)?;
+                    chip.memory_mut()[addr..end].copy_from_slice(&bytes);
+                    Some(())
+                });
+                match written {
+                    Some(()) => self.send("OK"),
+                    None => self.send("E01"),
+                }
+            }
+            "Z" | "z" => {
+                let mut fields = args.split(',');
+                let kind = fields.next();
+                let addr = fields
+                    .next()
+                    .and_then(|a| usize::from_str_radix(a, 16).ok());
+                match (kind, addr) {
+                    (Some("0"), Some(addr)) => {
+                        if cmd == "Z" {
+                            self.breakpoints.insert(addr);
+                        } else {
+                            self.breakpoints.remove(&addr);
+                        }
+                        self.send("OK")
+                    }
+                    // Only software breakpoints are supported
+                    _ => self.send(""),
+                }
+            }
+            "s" => {
+                if let Err(e) = chip.step() {
+                    log::error!("{e}");
+                    return self.send(&format!("S{SIGILL:02x}"));
+                }
+                self.resuming = true;
+                self.send(&format!("S{SIGTRAP:02x}"))
+            }
+            "c" => {
+                self.state = State::Running;
+                Ok(())
+            }
+            "k" => {
+                self.send("OK")?;
+                self.disconnect();
+                Ok(())
+            }
+            "D" => {
+                self.send("OK")?;
+                self.state = State::Detached;
+                self.disconnect();
+                Ok(())
+            }
+            "H" => self.send("OK"),
+            "q" | "Q" => self.handle_query(chip, packet),
+            _ => self.send(""),
+        }
+    }
+
+    fn handle_query(&mut self, chip: &mut Chip8, packet: &str) -> io::Result<()> {
+        if packet.starts_with("qSupported") {
+            return self.send(&format!(
+                "PacketSize={PACKET_SIZE:x};qXfer:features:read+;swbreak+;QStartNoAckMode+"
+            ));
+        }
+
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let Some((offset, len)) = parse_addr_len(range) else {
+                return self.send("E01");
+            };
+            let xml = TARGET_XML.as_bytes();
+            let start = offset.min(xml.len());
+            let end = offset.saturating_add(len).min(xml.len());
+            let prefix = if end == xml.len() { "l" } else { "m" };
+            let chunk = String::from_utf8_lossy(&xml[start..end]);
+            return self.send(&format!("{prefix}{chunk}"));
+        }
+
+        if let Some(cmd) = packet.strip_prefix("qRcmd,") {
+            let cmd = from_hex(cmd).map(|c| String::from_utf8_lossy(&c).into_owned());
+            return self.monitor(chip, cmd.as_deref().unwrap_or_default());
+        }
+
+        match packet {
+            "QStartNoAckMode" => {
+                self.send("OK")?;
+                self.no_ack = true;
+                Ok(())
+            }
+            "qAttached" => self.send("1"),
+            "qC" => self.send("QC1"),
+            "qfThreadInfo" => self.send("m1"),
+            "qsThreadInfo" => self.send("l"),
+            _ => self.send(""),
+        }
+    }
+
+    /// Run a `monitor` command of the client
+    fn monitor(&mut self, chip: &mut Chip8, cmd: &str) -> io::Result<()> {
+        let output = match cmd.trim() {
+            "reset" => {
+                chip.reset();
+                "Machine reset\n"
+            }
+            "reset hard" => {
+                chip.hard_reset();
+                "Machine reset, the ROM is reloaded\n"
+            }
+            _ => "Commands: reset, reset hard\n",
+        };
+        // Console output is sent before the result
+        self.send(&format!("O{}", to_hex(output.as_bytes())))?;
+        self.send("OK")
+    }
+
+    /// Report to the client that the machine stopped
+    fn stop(&mut self, signal: u8) -> io::Result<()> {
+        self.state = State::Halted;
+        self.resuming = true;
+        self.send(&format!("S{signal:02x}"))
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        log::debug!("gdb -> {data}");
+        let packet = format!("${data}#{:02x}", checksum(data.as_bytes()));
+        self.write_all(packet.as_bytes())
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let Some(conn) = self.conn.as_mut() else {
+            return Ok(());
+        };
+
+        // The socket is non blocking so we need to retry until everything
+        // is sent.
+        let mut sent = 0;
+        while sent < bytes.len() {
+            match conn.write(&bytes[sent..]) {
+                Ok(n) => sent += n,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::yield_now(),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Return the register `n` encoded in hexadecimal (little endian)
+fn read_register(chip: &Chip8, n: usize) -> Option<String> {
+    match n {
+        0..=15 => Some(to_hex(&[chip.vregs()[n]])),
+        16 => Some(to_hex(&chip.i().to_le_bytes())),
+        17 => Some(to_hex(&(chip.pc() as u16).to_le_bytes())),
+        18 => Some(to_hex(&[chip.stack().len() as u8])),
+        19 => Some(to_hex(&[chip.delay_timer() as u8])),
+        20 => Some(to_hex(&[chip.sound_timer() as u8])),
+        _ => None,
+    }
+}
+
+/// Return the end of the `len` bytes at `addr` if they are in the memory
+fn in_memory(chip: &Chip8, addr: usize, len: usize) -> Option<usize> {
+    addr.checked_add(len)
+        .filter(|end| *end <= chip.memory().len())
+}
+
+/// Parse "addr,length" where both are in hexadecimal
+fn parse_addr_len(s: &str) -> Option<(usize, usize)> {
+    let (addr, len) = s.split_once(',')?;
+    Some((
+        usize::from_str_radix(addr, 16).ok()?,
+        usize::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0_u8, |sum, b| sum.wrapping_add(*b))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
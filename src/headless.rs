@@ -0,0 +1,129 @@
+//! ## Headless runner
+//! Runs a [`Chip8`] without any window. Time is counted in frames: a frame
+//! is `ipf` instructions and the key script is applied at the beginning of
+//! each frame. Optional recorders render the sound and capture the screen at
+//! the end of each frame.
+
+use serde_json::json;
+
+use crate::audio::Recorder;
+use crate::emulator::{Chip8, Chip8Error};
+use crate::keyscript::{KeyScript, Replay};
+use crate::video::VideoRecorder;
+
+/// Instructions per frame. The frontend runs 360 instructions per second,
+/// so it is the same speed at 60 frames per second.
+pub const DEFAULT_IPF: usize = 6;
+/// Duration of a frame in seconds
+pub const FRAME_DURATION: f64 = 1.0 / 60.0;
+
+pub struct Headless {
+    pub chip: Chip8,
+    ipf: usize,
+    script: Replay,
+    cycles: u64,
+    recorder: Option<Recorder>,
+    video: Option<VideoRecorder>,
+}
+
+impl Headless {
+    pub fn new(chip: Chip8, ipf: usize, script: KeyScript) -> Self {
+        Headless {
+            chip,
+            ipf: ipf.max(1),
+            script: Replay::new(script),
+            cycles: 0,
+            recorder: None,
+            video: None,
+        }
+    }
+
+    /// Number of instructions executed so far
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Number of frames started so far
+    pub fn frames(&self) -> u64 {
+        self.cycles.div_ceil(self.ipf as u64)
+    }
+
+    /// Set the recorder that captures the screen of each frame. Passing None
+    /// stops the recording and finishes the file.
+    pub fn set_video(&mut self, video: Option<VideoRecorder>) {
+        if let Some(old) = std::mem::replace(&mut self.video, video) {
+            if let Err(e) = old.finish() {
+                log::error!("Failed to finish the video recording: {e}");
+            }
+        }
+    }
+
+    /// Execute one instruction
+    pub fn step(&mut self) -> Result<(), Chip8Error> {
+        let ipf = self.ipf as u64;
+        if self.cycles.is_multiple_of(ipf) {
+            self.script.advance(&mut self.chip);
+        }
+
+        self.chip.step()?;
+        self.cycles += 1;
+
+        if self.cycles.is_multiple_of(ipf) {
+            self.chip.tick_timers();
+            if let Some(recorder) = &mut self.recorder {
+                if let Err(e) = recorder.advance(FRAME_DURATION, self.chip.sound_timer() > 0) {
+                    log::error!("Audio recording is stopped: {e}");
+                    self.recorder = None;
+                }
+            }
+            if let Some(video) = &mut self.video {
+                if video.resolution() != self.chip.resolution() {
+                    log::warn!("Video recording is stopped by the change of resolution");
+                    self.set_video(None);
+                } else if let Err(e) = video.advance(FRAME_DURATION, self.chip.get_framebuffer()) {
+                    log::error!("Video recording is stopped: {e}");
+                    self.video = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the recorder that renders the sound of each frame. Passing None
+    /// stops the recording and finishes the file.
+    pub fn set_recorder(&mut self, recorder: Option<Recorder>) {
+        if let Some(old) = std::mem::replace(&mut self.recorder, recorder) {
+            if let Err(e) = old.finish() {
+                log::error!("Failed to finish the audio recording: {e}");
+            }
+        }
+    }
+
+    pub fn run_cycles(&mut self, cycles: u64) -> Result<(), Chip8Error> {
+        for _ in 0..cycles {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    pub fn run_frames(&mut self, frames: u64) -> Result<(), Chip8Error> {
+        self.run_cycles(frames * self.ipf as u64)
+    }
+
+    /// Return the state of the machine as JSON. Addresses are written in
+    /// hexadecimal like in traces.
+    pub fn state_json(&self) -> String {
+        let chip = &self.chip;
+        let state = json!({
+            "frames": self.frames(),
+            "cycles": self.cycles,
+            "pc": format!("{:#06x}", chip.pc()),
+            "i": format!("{:#06x}", chip.i()),
+            "v": chip.vregs(),
+            "stack": chip.stack().iter().map(|a| format!("{a:#06x}")).collect::<Vec<String>>(),
+            "dt": chip.delay_timer(),
+            "st": chip.sound_timer(),
+        });
+        serde_json::to_string_pretty(&state).unwrap()
+    }
+}
@@ -0,0 +1,172 @@
+//! ## Framebuffer images
+//! The framebuffer stores 8 pixels per byte, the most significant bit being
+//! the leftmost pixel. This module converts it to:
+//! - ASCII art: `#` for pixels that are set, `.` otherwise
+//! - plain PBM (P1): a text format where 1 is a pixel that is set
+//! - PNG: RGB, encoded without compression so we don't need a zlib
+//!
+//! ### Links
+//! - [PBM](https://netpbm.sourceforge.net/doc/pbm.html)
+//! - [PNG](https://www.w3.org/TR/png/)
+
+/// Return true if the pixel at (x, y) is set
+pub fn is_set(fb: &[u8], width: usize, x: usize, y: usize) -> bool {
+    let idx = y * width + x;
+    fb[idx / 8] & (0x80 >> (idx % 8)) != 0
+}
+
+/// Return one line of `#` and `.` per row of pixels
+pub fn to_ascii(fb: &[u8], width: usize, height: usize) -> Vec<String> {
+    (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| if is_set(fb, width, x, y) { '#' } else { '.' })
+                .collect()
+        })
+        .collect()
+}
+
+/// Encode the framebuffer as a plain PBM
+pub fn to_pbm(fb: &[u8], width: usize, height: usize) -> String {
+    let mut pbm = format!("P1\n{width} {height}\n");
+    for y in 0..height {
+        let row: Vec<&str> = (0..width)
+            .map(|x| if is_set(fb, width, x, y) { "1" } else { "0" })
+            .collect();
+        pbm.push_str(&row.join(" "));
+        pbm.push('\n');
+    }
+    pbm
+}
+
+/// Parse a plain PBM and return its width, its height and the pixels packed
+/// like the framebuffer.
+pub fn from_pbm(pbm: &str) -> Option<(usize, usize, Vec<u8>)> {
+    // Comments start with a # and go until the end of the line
+    let mut tokens = pbm
+        .lines()
+        .map(|l| l.split('#').next().unwrap_or_default())
+        .flat_map(|l| l.split_whitespace());
+
+    if tokens.next()? != "P1" {
+        return None;
+    }
+    let width: usize = tokens.next()?.parse().ok()?;
+    let height: usize = tokens.next()?.parse().ok()?;
+
+    // In plain PBM pixels don't need to be separated by whitespaces
+    let bits: Vec<bool> = tokens.flat_map(|t| t.chars()).map(|c| c == '1').collect();
+    if bits.len() != width * height {
+        return None;
+    }
+
+    let mut fb = vec![0; (width * height).div_ceil(8)];
+    for (idx, _) in bits.iter().enumerate().filter(|(_, b)| **b) {
+        fb[idx / 8] |= 0x80 >> (idx % 8);
+    }
+    Some((width, height, fb))
+}
+
+/// Convert the framebuffer to RGB pixels. Each pixel of the framebuffer is
+/// drawn as a `scale` x `scale` square.
+pub fn to_rgb(
+    fb: &[u8],
+    width: usize,
+    height: usize,
+    scale: usize,
+    fg: [u8; 3],
+    bg: [u8; 3],
+) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(width * height * scale * scale * 3);
+    for y in 0..height * scale {
+        for x in 0..width * scale {
+            let color = if is_set(fb, width, x / scale, y / scale) {
+                fg
+            } else {
+                bg
+            };
+            rgb.extend_from_slice(&color);
+        }
+    }
+    rgb
+}
+
+/// Encode RGB pixels as a PNG
+pub fn to_png(rgb: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+    // 8 bits per channel, color type 2 is RGB
+    let mut ihdr = vec![];
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+    png_chunk(&mut png, b"IHDR", &ihdr);
+
+    // Each scanline starts with its filter type, 0 is none
+    let mut raw = Vec::with_capacity(height * (width * 3 + 1));
+    for line in rgb.chunks(width * 3) {
+        raw.push(0);
+        raw.extend_from_slice(line);
+    }
+    png_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    png_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+
+    let mut crc_data = kind.to_vec();
+    crc_data.extend_from_slice(data);
+    png.extend_from_slice(&crc32(&crc_data).to_be_bytes());
+}
+
+/// Wrap data in a zlib stream made of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // CMF: deflate with a 32K window, FLG: no dictionary and a valid check
+    let mut z = vec![0x78, 0x01];
+
+    let mut blocks = data.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        // An empty stream still needs a final block
+        z.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        z.push(last as u8);
+        z.extend_from_slice(&len.to_le_bytes());
+        z.extend_from_slice(&(!len).to_le_bytes());
+        z.extend_from_slice(block);
+    }
+
+    z.extend_from_slice(&adler32(data).to_be_bytes());
+    z
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,240 @@
+//! ## Keymaps
+//! A keymap gives the keys of the host keyboard used for each key of the
+//! CHIP-8 keypad. The presets place the keypad on the left of the keyboard,
+//! so keys are at the same place as on the COSMAC VIP:
+//!
+//! ```text
+//!  CHIP-8      QWERTY      AZERTY
+//!  1 2 3 C     1 2 3 4     1 2 3 4
+//!  4 5 6 D     Q W E R     A Z E R
+//!  7 8 9 E     A S D F     Q S D F
+//!  A 0 B F     Z X C V     W X C V
+//! ```
+//!
+//! A custom keymap is a file with one `<CHIP-8 key> <host key>...` per line,
+//! for example `5 w up` maps the key 5 of the keypad to both W and the up
+//! arrow. Host keys are named after the constants of
+//! [`keys`](crate::raylib_bindings::keys), without the `KEY_` prefix. Lines
+//! starting with `#` are comments and keys that are not listed are not
+//! mapped.
+//!
+//! Gamepads are written `pad<N>:<button>` or `pad<N>:<axis><+ or ->`, with
+//! the names of the constants of
+//! [`gamepad`](crate::raylib_bindings::gamepad) without their prefix. For
+//! example, two players can play Pong with the D-pads and the left sticks of
+//! the first two gamepads:
+//!
+//! ```text
+//! 1 pad0:left_face_up pad0:left_y-
+//! 4 pad0:left_face_down pad0:left_y+
+//! c pad1:left_face_up pad1:left_y-
+//! d pad1:left_face_down pad1:left_y+
+//! ```
+//!
+//! The presets also map the D-pad of the first gamepad to 2, 4, 6 and 8, the
+//! usual directions of CHIP-8 games, and its bottom face button to 5.
+
+use std::fs;
+use std::path::Path;
+
+use crate::raylib_bindings::gamepad::{self, *};
+use crate::raylib_bindings::keys::{self, *};
+#[cfg(feature = "raylib")]
+use crate::raylib_bindings::{
+    get_gamepad_axis_movement, is_gamepad_available, is_gamepad_button_down, is_key_down,
+};
+
+/// Names of the presets accepted by [`Keymap::preset`]
+pub const PRESETS: [&str; 2] = ["qwerty", "azerty"];
+
+/// Keys of the CHIP-8 keypad, row by row
+const KEYPAD: [usize; 16] = [
+    0x1, 0x2, 0x3, 0xC, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x7, 0x8, 0x9, 0xE, //
+    0xA, 0x0, 0xB, 0xF, //
+];
+
+/// Axes are down when pushed further than this from the centre
+#[cfg(feature = "raylib")]
+const AXIS_THRESHOLD: f32 = 0.5;
+
+/// Something of the host that can press a CHIP-8 key
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Key(i32),
+    Button {
+        pad: i32,
+        button: i32,
+    },
+    /// An axis pushed toward its positive or its negative values
+    Axis {
+        pad: i32,
+        axis: i32,
+        positive: bool,
+    },
+}
+
+impl Input {
+    /// Parse a key name, or a gamepad input like `pad0:left_face_up`
+    pub fn from_name(name: &str) -> Option<Input> {
+        let Some((pad, input)) = name.split_once(':') else {
+            return keys::from_name(name).map(Input::Key);
+        };
+        let pad = pad.to_ascii_lowercase();
+        let pad = pad.strip_prefix("pad")?.parse().ok()?;
+
+        if let Some(button) = gamepad::button_from_name(input) {
+            return Some(Input::Button { pad, button });
+        }
+        let (axis, positive) = match input.strip_suffix('+') {
+            Some(axis) => (axis, true),
+            None => (input.strip_suffix('-')?, false),
+        };
+        let axis = gamepad::axis_from_name(axis)?;
+        Some(Input::Axis {
+            pad,
+            axis,
+            positive,
+        })
+    }
+
+    /// Return whether the input is down, inputs of disconnected gamepads
+    /// never are
+    #[cfg(feature = "raylib")]
+    pub fn is_down(self) -> bool {
+        match self {
+            Input::Key(key) => is_key_down(key),
+            Input::Button { pad, .. } | Input::Axis { pad, .. } if !is_gamepad_available(pad) => {
+                false
+            }
+            Input::Button { pad, button } => is_gamepad_button_down(pad, button),
+            Input::Axis {
+                pad,
+                axis,
+                positive,
+            } => {
+                let movement = get_gamepad_axis_movement(pad, axis);
+                if positive {
+                    movement > AXIS_THRESHOLD
+                } else {
+                    movement < -AXIS_THRESHOLD
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Keymap {
+    /// Host inputs of each CHIP-8 key
+    keys: [Vec<Input>; 16],
+}
+
+impl Keymap {
+    /// Build a keymap from the host keys placed like the keypad, row by row,
+    /// and the default gamepad buttons
+    fn from_layout(layout: [i32; 16]) -> Keymap {
+        let mut keymap = Keymap::default();
+        for (chip_key, host_key) in KEYPAD.iter().zip(layout) {
+            keymap.keys[*chip_key].push(Input::Key(host_key));
+        }
+
+        let pad = 0;
+        for (chip_key, button) in [
+            (0x2, BUTTON_LEFT_FACE_UP),
+            (0x4, BUTTON_LEFT_FACE_LEFT),
+            (0x6, BUTTON_LEFT_FACE_RIGHT),
+            (0x8, BUTTON_LEFT_FACE_DOWN),
+            (0x5, BUTTON_RIGHT_FACE_DOWN),
+        ] {
+            keymap.keys[chip_key].push(Input::Button { pad, button });
+        }
+        keymap
+    }
+
+    pub fn qwerty() -> Keymap {
+        Keymap::from_layout([
+            KEY_ONE, KEY_TWO, KEY_THREE, KEY_FOUR, //
+            KEY_Q, KEY_W, KEY_E, KEY_R, //
+            KEY_A, KEY_S, KEY_D, KEY_F, //
+            KEY_Z, KEY_X, KEY_C, KEY_V, //
+        ])
+    }
+
+    pub fn azerty() -> Keymap {
+        Keymap::from_layout([
+            KEY_ONE, KEY_TWO, KEY_THREE, KEY_FOUR, //
+            KEY_A, KEY_Z, KEY_E, KEY_R, //
+            KEY_Q, KEY_S, KEY_D, KEY_F, //
+            KEY_W, KEY_X, KEY_C, KEY_V, //
+        ])
+    }
+
+    pub fn preset(name: &str) -> Option<Keymap> {
+        match name {
+            "qwerty" => Some(Keymap::qwerty()),
+            "azerty" => Some(Keymap::azerty()),
+            _ => None,
+        }
+    }
+
+    /// Return the preset named `name`, or load it from a file otherwise
+    pub fn from_option(name: &str) -> Result<Keymap, String> {
+        match Keymap::preset(name) {
+            Some(keymap) => Ok(keymap),
+            None if Path::new(name).is_file() => Keymap::load(Path::new(name)),
+            None => Err(format!(
+                "'{name}' is neither a keymap preset ({}) nor a file",
+                PRESETS.join(", ")
+            )),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Keymap, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Keymap::parse(&content).map_err(|e| format!("{}:{e}", path.display()))
+    }
+
+    /// Parse the content of a keymap file, errors start with the line number
+    pub fn parse(content: &str) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+
+        for (n, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let chip_key = fields.next().unwrap_or_default();
+            let chip_key = u8::from_str_radix(chip_key, 16)
+                .ok()
+                .filter(|k| *k < 16)
+                .ok_or_else(|| format!("{}: invalid CHIP-8 key '{chip_key}'", n + 1))?;
+
+            let mut inputs = fields.peekable();
+            if inputs.peek().is_none() {
+                return Err(format!("{}: expected '<CHIP-8 key> <host key>...'", n + 1));
+            }
+            for input in inputs {
+                let input = Input::from_name(input)
+                    .ok_or_else(|| format!("{}: unknown key '{input}'", n + 1))?;
+                keymap.keys[chip_key as usize].push(input);
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    /// Return the host inputs of the CHIP-8 key `chip_key`
+    pub fn inputs(&self, chip_key: usize) -> &[Input] {
+        &self.keys[chip_key]
+    }
+
+    /// Return whether one of the inputs of `chip_key` is down
+    #[cfg(feature = "raylib")]
+    pub fn is_down(&self, chip_key: usize) -> bool {
+        self.keys[chip_key].iter().any(|input| input.is_down())
+    }
+}
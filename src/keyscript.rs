@@ -0,0 +1,153 @@
+//! ## Scripted key presses
+//! A key script lists when CHIP-8 keys are pressed and released. Each line
+//! is `<frame> <key> <down|up>` where the key is in hexadecimal (0 to F).
+//! The machine can also be reset with `<frame> reset` (see
+//! [`Chip8::reset`](crate::emulator::Chip8::reset)) or `<frame> hard-reset`.
+//! Empty lines and lines starting with `#` are ignored.
+//!
+//! ```text
+//! # Press 5 during 10 frames to start the game
+//! 60 5 down
+//! 70 5 up
+//! # Then start again
+//! 300 reset
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::emulator::Chip8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Key { key: usize, pressed: bool },
+    Reset,
+    HardReset,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyEvent {
+    pub frame: u64,
+    pub action: Action,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct KeyScript {
+    /// Events sorted by frame
+    events: Vec<KeyEvent>,
+}
+
+impl KeyScript {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{e}", path.display()),
+            )
+        })
+    }
+
+    /// Parse a script, the error contains the line number and the reason
+    pub fn parse(script: &str) -> Result<Self, String> {
+        let mut events = vec![];
+
+        for (n, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (frame, action) = match fields[..] {
+                [frame, "reset"] => (frame, Action::Reset),
+                [frame, "hard-reset"] => (frame, Action::HardReset),
+                [frame, key, action] => {
+                    let key = match usize::from_str_radix(key, 16) {
+                        Ok(k) if k < 16 => k,
+                        _ => return Err(format!("{}: invalid key '{key}'", n + 1)),
+                    };
+                    let pressed = match action {
+                        "down" => true,
+                        "up" => false,
+                        _ => return Err(format!("{}: invalid action '{action}'", n + 1)),
+                    };
+                    (frame, Action::Key { key, pressed })
+                }
+                _ => {
+                    return Err(format!(
+                        "{}: expected '<frame> <key> <down|up>', '<frame> reset' or '<frame> hard-reset'",
+                        n + 1
+                    ))
+                }
+            };
+
+            let frame = frame
+                .parse::<u64>()
+                .map_err(|_| format!("{}: invalid frame '{frame}'", n + 1))?;
+
+            events.push(KeyEvent { frame, action });
+        }
+
+        // Keep the order of the file for events of the same frame
+        events.sort_by_key(|e| e.frame);
+        Ok(KeyScript { events })
+    }
+
+    /// Return the events of `frame`
+    pub fn events_at(&self, frame: u64) -> impl Iterator<Item = &KeyEvent> {
+        let start = self.events.partition_point(|e| e.frame < frame);
+        self.events[start..]
+            .iter()
+            .take_while(move |e| e.frame == frame)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+/// Plays a key script frame after frame in the frontend. Keys it presses are
+/// added to the ones of the player.
+pub struct Replay {
+    script: KeyScript,
+    frame: u64,
+    keys: [bool; 16],
+}
+
+impl Replay {
+    pub fn new(script: KeyScript) -> Self {
+        Replay {
+            script,
+            frame: 0,
+            keys: [false; 16],
+        }
+    }
+
+    /// Play the script again from the first frame
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.keys = [false; 16];
+    }
+
+    /// Apply the events of the current frame to `chip` and go to the next
+    /// frame
+    pub fn advance(&mut self, chip: &mut Chip8) {
+        for e in self.script.events_at(self.frame) {
+            match e.action {
+                Action::Key { key, pressed } => {
+                    self.keys[key] = pressed;
+                    chip.set_key(key, pressed);
+                }
+                Action::Reset => chip.reset(),
+                Action::HardReset => chip.hard_reset(),
+            }
+        }
+        self.frame += 1;
+    }
+
+    /// Return true if the script holds `key` down
+    pub fn is_down(&self, key: usize) -> bool {
+        self.keys[key]
+    }
+}
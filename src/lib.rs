@@ -1,2 +1,23 @@
+pub mod audio;
+pub mod clock;
+pub mod config;
+pub mod crash;
+pub mod dap;
+pub mod database;
+pub mod disasm;
+pub mod effects;
 pub mod emulator;
+pub mod gdb;
+pub mod headless;
+pub mod image;
+pub mod keymap;
+pub mod keyscript;
+pub mod library;
+pub mod palette;
 pub mod raylib_bindings;
+pub mod sha1;
+pub mod timestamp;
+pub mod trace;
+pub mod video;
+pub mod viewport;
+pub mod watch;
@@ -0,0 +1,293 @@
+//! ## ROM library
+//! Without a ROM on the command line, the frontend opens a browser of the
+//! ROMs of a directory (`--rom-dir`, `chip8-roms/` by default). The ROMs
+//! played recently come first, they are kept in a TOML file:
+//!
+//! ```toml
+//! [[rom]]
+//! path = "/home/user/chip8-roms/pong.ch8"
+//! played = 1714571102
+//! ```
+//!
+//! where `played` is the last time the ROM was started, in seconds since the
+//! epoch.
+//!
+//! ROMs can also be dropped on the window. An Octo source (`.8o`) can't be
+//! run, the ROM assembled next to it is loaded instead, for example
+//! `game.ch8` for `game.8o`.
+
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use toml::{Table, Value};
+
+use crate::config::Variant;
+use crate::database::{Database, RomInfo};
+
+/// File of the recent ROMs, in the current directory
+pub const RECENT_FILE: &str = "chip8-recent.toml";
+/// Number of ROMs kept in the recent list
+pub const MAX_RECENT: usize = 10;
+/// Extensions of the files listed by the browser
+pub const EXTENSIONS: [&str; 3] = ["ch8", "sc8", "xo8"];
+/// Extension of Octo sources
+pub const SOURCE_EXTENSION: &str = "8o";
+
+/// ROMs started recently, the last one first
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recent {
+    file: PathBuf,
+    roms: Vec<(PathBuf, u64)>,
+}
+
+impl Recent {
+    /// Return an empty list saved in `file`
+    pub fn new(file: &Path) -> Recent {
+        Recent {
+            file: file.to_path_buf(),
+            roms: vec![],
+        }
+    }
+
+    /// Read the list saved in `file`, it is empty if the file doesn't exist
+    pub fn load(file: &Path) -> Result<Recent, String> {
+        let mut recent = Recent::new(file);
+        let content = match fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(recent),
+            Err(e) => return Err(format!("{}: {e}", file.display())),
+        };
+
+        let invalid = || format!("{}: expected [[rom]] tables with a path", file.display());
+        let table: Table = content
+            .parse()
+            .map_err(|e| format!("{}: {e}", file.display()))?;
+        let roms = match table.get("rom") {
+            Some(Value::Array(roms)) => roms,
+            Some(_) => return Err(invalid()),
+            None => return Ok(recent),
+        };
+        for rom in roms {
+            let path = rom
+                .get("path")
+                .and_then(Value::as_str)
+                .ok_or_else(invalid)?;
+            let played = rom.get("played").and_then(Value::as_integer).unwrap_or(0);
+            recent
+                .roms
+                .push((PathBuf::from(path), played.max(0) as u64));
+        }
+        recent.roms.truncate(MAX_RECENT);
+        Ok(recent)
+    }
+
+    /// Write the list in its file
+    pub fn save(&self) -> io::Result<()> {
+        let roms = self
+            .roms
+            .iter()
+            .map(|(path, played)| {
+                let mut rom = Table::new();
+                rom.insert(
+                    "path".to_string(),
+                    Value::String(path.display().to_string()),
+                );
+                rom.insert("played".to_string(), Value::Integer(*played as i64));
+                Value::Table(rom)
+            })
+            .collect();
+        let mut table = Table::new();
+        table.insert("rom".to_string(), Value::Array(roms));
+        fs::write(&self.file, table.to_string())
+    }
+
+    /// Put `rom` at the top of the list, it was played at `secs` after the
+    /// epoch
+    pub fn add(&mut self, rom: &Path, secs: u64) {
+        let rom = absolute(rom);
+        self.roms.retain(|(path, _)| *path != rom);
+        self.roms.insert(0, (rom, secs));
+        self.roms.truncate(MAX_RECENT);
+    }
+
+    /// Return when `rom` was last played, in seconds since the epoch
+    pub fn played(&self, rom: &Path) -> Option<u64> {
+        let rom = absolute(rom);
+        self.roms
+            .iter()
+            .find(|(path, _)| *path == rom)
+            .map(|(_, played)| *played)
+    }
+
+    /// Return the paths of the ROMs, the last played first
+    pub fn roms(&self) -> impl Iterator<Item = &Path> {
+        self.roms.iter().map(|(path, _)| path.as_path())
+    }
+}
+
+/// Return the absolute path of `path`, so a ROM is the same whatever the
+/// directory it was opened from
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A ROM shown by the browser
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomEntry {
+    pub path: PathBuf,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// Variant of the database, or the one of the extension
+    pub variant: Variant,
+    /// Last time the ROM was played, in seconds since the epoch
+    pub played: Option<u64>,
+    /// What the database knows about the ROM
+    pub info: Option<RomInfo>,
+}
+
+impl RomEntry {
+    fn new(path: &Path, recent: &Recent, database: &Database) -> io::Result<RomEntry> {
+        let rom = fs::read(path)?;
+        let info = database.get(&rom).cloned();
+        Ok(RomEntry {
+            path: path.to_path_buf(),
+            size: rom.len() as u64,
+            variant: info
+                .as_ref()
+                .and_then(|i| i.variant)
+                .unwrap_or(Variant::from_path(path)),
+            played: recent.played(path),
+            info,
+        })
+    }
+
+    /// Return the title of the ROM in the database, or the name of its file
+    pub fn title(&self) -> String {
+        match &self.info {
+            Some(info) => info.title.clone(),
+            None => self.name(),
+        }
+    }
+
+    /// Return the name of the file of the ROM
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map_or(self.path.display().to_string(), |n| {
+                n.to_string_lossy().to_string()
+            })
+    }
+}
+
+/// Return the ROM to load for a file dropped on the window: the file itself
+/// if it is a ROM, the ROM assembled next to it if it is an Octo source
+pub fn rom_for(path: &Path) -> Result<PathBuf, String> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if EXTENSIONS.iter().any(|x| ext.eq_ignore_ascii_case(x)) {
+        return Ok(path.to_path_buf());
+    }
+    if !ext.eq_ignore_ascii_case(SOURCE_EXTENSION) {
+        return Err(format!(
+            "{}: expected a ROM ({}) or an Octo source ({SOURCE_EXTENSION})",
+            path.display(),
+            EXTENSIONS.join(", ")
+        ));
+    }
+
+    EXTENSIONS
+        .iter()
+        .map(|x| path.with_extension(x))
+        .find(|rom| rom.is_file())
+        .ok_or_else(|| {
+            format!(
+                "{}: the source must be assembled in a ROM next to it, like {}",
+                path.display(),
+                path.with_extension(EXTENSIONS[0]).display()
+            )
+        })
+}
+
+/// Return the ROMs of `dir` sorted by name, with the time they were played
+/// from `recent` and what `database` knows about them
+pub fn scan(dir: &Path, recent: &Recent, database: &Database) -> io::Result<Vec<RomEntry>> {
+    let mut entries = vec![];
+    for file in fs::read_dir(dir)? {
+        let path = file?.path();
+        let is_rom = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| EXTENSIONS.iter().any(|x| e.eq_ignore_ascii_case(x)));
+        if is_rom && path.is_file() {
+            entries.push(RomEntry::new(&path, recent, database)?);
+        }
+    }
+    entries.sort_by_key(RomEntry::name);
+    Ok(entries)
+}
+
+/// A list of ROMs with a selected one: the recent ROMs that still exist,
+/// then the other ROMs of a directory
+pub struct Browser {
+    dir: PathBuf,
+    entries: Vec<RomEntry>,
+    selected: usize,
+}
+
+impl Browser {
+    pub fn new(dir: &Path, recent: &Recent, database: &Database) -> Browser {
+        let mut entries: Vec<RomEntry> = recent
+            .roms()
+            .filter_map(|path| RomEntry::new(path, recent, database).ok())
+            .collect();
+        match scan(dir, recent, database) {
+            Ok(roms) => {
+                for rom in roms {
+                    if rom.played.is_none() {
+                        entries.push(rom);
+                    }
+                }
+            }
+            Err(e) => log::warn!("Cannot list the ROMs of {}: {e}", dir.display()),
+        }
+
+        Browser {
+            dir: dir.to_path_buf(),
+            entries,
+            selected: 0,
+        }
+    }
+
+    /// Return the directory of the ROMs
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn entries(&self) -> &[RomEntry] {
+        &self.entries
+    }
+
+    pub fn selected(&self) -> Option<&RomEntry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// Move the selection by `delta` entries, it stops at the first and the
+    /// last ones
+    pub fn move_by(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// Return the entries to show in `rows` lines, the selected one is
+    /// always visible
+    pub fn visible(&self, rows: usize) -> Range<usize> {
+        let rows = rows.max(1);
+        let start = (self.selected / rows) * rows;
+        start..(start + rows).min(self.entries.len())
+    }
+}
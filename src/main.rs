@@ -1,13 +1,183 @@
-use chip8_emulator::emulator::Chip8;
+use chip8_emulator::audio::{Beeper, Recorder, SAMPLE_RATE};
+use chip8_emulator::clock::{Clock, TICK};
+use chip8_emulator::config::{Config, Variant, CONFIG_FILE};
+use chip8_emulator::crash::{self, Failure};
+use chip8_emulator::database::{Database, RomInfo};
+use chip8_emulator::effects::{Overlays, Phosphor};
+use chip8_emulator::emulator::{Chip8, Chip8Error, Snapshot};
+use chip8_emulator::gdb::GdbStub;
+use chip8_emulator::image;
+use chip8_emulator::keymap::Keymap;
+use chip8_emulator::keyscript::{KeyScript, Replay};
+use chip8_emulator::library::{self, Browser, Recent, RECENT_FILE};
+use chip8_emulator::palette::{self, Palette};
+use chip8_emulator::timestamp;
+use chip8_emulator::trace::Tracer;
+use chip8_emulator::video::VideoRecorder;
+use chip8_emulator::viewport::Viewport;
+use chip8_emulator::watch::RomWatcher;
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::time::{Duration, Instant};
 
 use chip8_emulator::raylib_bindings::{
-    begin_drawing, clear_background, close_window, color, draw_rectangle, end_drawing, init_window,
-    is_key_pressed, is_key_released, keys, set_target_fps, window_should_close,
+    begin_drawing, begin_mode_2d,
+    camera2d::Camera2D,
+    clear_background, close_audio_device, close_window, color, draw_rectangle, draw_text,
+    draw_texture_pro, end_drawing, end_mode_2d, get_frame_time, get_mouse_position,
+    get_mouse_wheel_move, get_screen_height, get_screen_width, init_audio_device, init_window,
+    is_audio_device_ready, is_audio_stream_processed, is_file_dropped, is_key_down, is_key_pressed,
+    is_window_fullscreen, keys, load_audio_stream, load_dropped_files, load_texture_from_rgba,
+    play_audio_stream,
+    rectangle::Rectangle,
+    set_audio_stream_buffer_size_default, set_config_flags, set_target_fps, set_texture_filter,
+    set_window_min_size,
+    texture::{Texture2D, TEXTURE_FILTER_POINT},
+    toggle_fullscreen, unload_audio_stream, unload_dropped_files, unload_texture,
+    update_audio_stream, update_texture,
+    vector2::Vector2,
+    window_should_close, FLAG_WINDOW_RESIZABLE,
 };
 
-const RESOLUTION: (i32, i32) = (64, 32);
+/// Frames rendered per second, the emulation runs at 60 Hz whatever this rate
+const FPS: i32 = 60;
+/// Number of samples given to the audio stream at once (~23ms)
+const AUDIO_BUFFER_SIZE: usize = 1024;
+/// Key that mutes or unmutes the sound
+const MUTE_KEY: i32 = keys::KEY_M;
+/// Key that pauses or resumes the emulation
+const PAUSE_KEY: i32 = keys::KEY_P;
+/// Key that starts or stops the recording of a video
+const RECORD_KEY: i32 = keys::KEY_F9;
+/// Key that saves the screen in a PNG file
+const SCREENSHOT_KEY: i32 = keys::KEY_F12;
+/// Key that switches between the window and fullscreen
+const FULLSCREEN_KEY: i32 = keys::KEY_F11;
+/// Zoom added by a step of the mouse wheel
+const ZOOM_STEP: f32 = 0.25;
+const MAX_ZOOM: f32 = 16.0;
+/// Largest size of the pixels of the texture of the screen, for overlays
+const MAX_TEXTURE_SCALE: usize = 16;
+/// Key held to fast-forward
+const FAST_FORWARD_KEY: i32 = keys::KEY_TAB;
+/// Key that switches slow motion on or off
+const SLOW_MOTION_KEY: i32 = keys::KEY_L;
+/// Keys that run a frame or a single instruction while paused
+const FRAME_KEY: i32 = keys::KEY_N;
+const STEP_KEY: i32 = keys::KEY_I;
+/// Time given to each frame to fast-forward as fast as possible, so the
+/// window stays responsive at 60 frames per second
+const UNCAPPED_DURATION: Duration = Duration::from_millis(12);
+/// Key that resets the machine, a hard reset with Shift
+const RESET_KEY: i32 = keys::KEY_F5;
+/// Keys of the crash report
+const RELOAD_KEY: i32 = keys::KEY_F6;
+const DEBUGGER_KEY: i32 = keys::KEY_F7;
+/// Port of the gdb stub started from the crash report
+const DEBUGGER_PORT: u16 = 1234;
+/// Number of instructions of the history in the crash report
+const CRASH_HISTORY_LINES: usize = 10;
+/// Keys that save the state of the machine and restore it
+const SAVE_STATE_KEY: i32 = keys::KEY_F2;
+const LOAD_STATE_KEY: i32 = keys::KEY_F3;
+/// Key that switches to the next palette
+const PALETTE_KEY: i32 = keys::KEY_T;
+/// Key that opens or closes the ROM browser
+const BROWSER_KEY: i32 = keys::KEY_F1;
+/// Height of a line of the ROM browser
+const BROWSER_LINE_HEIGHT: i32 = 24;
+
+/// Options without value, they are set to true
+const FLAGS: [&str; 6] = [
+    "paused",
+    "scanlines",
+    "grid",
+    "fullscreen",
+    "integer-scale",
+    "watch",
+];
+
+const USAGE: &str = "usage: chip8-emulator [ROM] [OPTIONS]
+
+Without ROM, a browser of the ROMs of --rom-dir is opened.
+
+Options:
+    --scale <N>          initial size of a CHIP-8 pixel in the window (default: 20)
+    --integer-scale      round the size of pixels to an integer when resizing
+    --fullscreen         start in fullscreen (F11 to switch)
+    --ipf <N>            instructions per frame, at 60 frames per second (default: 6)
+    --palette <PALETTE>  classic, amber, green, lcd, high-contrast or 2 to 4 colours
+                         like #000000,#FFB000 (default: classic)
+    --foreground <RGB>   colour of pixels that are set, like #00FF00
+    --background <RGB>   colour of pixels that are not set, like #000000
+    --persistence <P>    off, blend (the last two frames) or fade, against flicker
+                         (default: off)
+    --fade-frames <N>    number of frames a pixel takes to fade out (default: 4)
+    --scanlines          darken the bottom half of each row of pixels
+    --grid               draw a grid between pixels
+    --variant <NAME>     chip8, schip or xochip (default: chip8)
+    --quirks <PRESET>    default, vip, schip or xochip (default: the one of the variant)
+    --keymap <KEYMAP>    qwerty, azerty or a keymap file (default: azerty)
+    --fast-forward <N>   speed while Tab is held, 0 is as fast as possible (default: 0)
+    --slow-motion <N>    speed in slow motion, below 1 (default: 0.25)
+    --seed <N>           seed of the random generator, for reproducible runs
+    --history <N>        number of executed instructions kept for crash bundles
+                         (default: 64)
+    --paused             start with the emulation paused (P to resume)
+    --tone <HZ>          frequency of the beep (default: 440)
+    --volume <N>         volume of the beep from 0 to 100 (default: 25)
+    --waveform <W>       square, triangle, sawtooth or sine (default: square)
+    --video-scale <N>    scale of recorded videos (default: 4)
+    --screenshot-scale <N>
+                         scale of screenshots, 1 is the native resolution (default: 1)
+    --watch              reload the ROM when its file changes
+    --rom-dir <DIR>      directory of the ROM browser (default: chip8-roms)
+    --database <FILE>    settings of known ROMs by SHA-1 (default: chip8-roms/database.json)
+    --replay <FILE>      play a key script from the start, and again after each reload
+                         (see chip8-headless --help)
+    --config <FILE>      read options from a TOML file (default: chip8.toml if it exists)
+    --gdb <PORT>         wait for a gdb client on PORT
+    --trace <FILE>       write a trace of executed instructions (.jsonl or .csv)
+    --wav <FILE>         render the sound of the session in a WAV file
+    --video <FILE>       record the screen from the start (.gif or .y4m)
+    -h, --help           print this help
+
+Options of the configuration file have the same names. Options of the table
+[rom.\"<file name>\"] only apply to this ROM, for example:
+
+    scale = 15
+    [rom.\"pong.ch8\"]
+    ipf = 10
+
+Known ROMs are configured by the database: the variant, the quirks, the instructions
+per frame and the palette of a ROM are found by its SHA-1, before the configuration file
+and the command line are applied.
+
+Keys: M mutes the sound, P pauses, N runs a frame and I an instruction while paused,
+Tab fast-forwards while held, L switches slow motion, T switches the palette,
+F5 resets the machine and Shift+F5 reloads the ROM in a cleared memory (hard reset),
+F2 saves the state of the machine and F3 restores it,
+F9 records a video, F11 switches to fullscreen and F12 saves a screenshot.
+The mouse wheel zooms. F1 opens the ROM browser to switch to another ROM, the
+ROMs played recently are listed first. A ROM (.ch8, .sc8 or .xo8) dropped on the
+window is loaded with the variant of its extension, for an Octo source (.8o) it is
+the ROM assembled next to it.
+
+With --watch, a ROM that changes on the disk is reloaded and the machine is reset. The
+quirks, the palette and the speed are kept. The state saved with F2 is restored with
+the new ROM, and the key script of --replay is played again.
+
+When the ROM fails or the emulator panics, a crash bundle is written in a
+chip8-crash-<date> directory and the emulation is frozen on a report of the error.
+From there F5 resets, F6 reads the ROM again from the disk and F7 starts a gdb stub on
+port 1234.";
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{msg}");
+    exit(2);
+}
 
 fn main() {
     env_logger::init();
@@ -15,115 +185,879 @@ fn main() {
     // First argument is the name of the binary
     let a: Vec<String> = env::args().collect();
 
-    if a.len() < 2 {
-        log::error!("You need to pass filename for the ROM");
-        exit(1);
+    if a.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        exit(0);
     }
+    // The ROM is optional, the browser is opened without it
+    let (mut rom_path, args) = match a.get(1) {
+        Some(arg) if !arg.starts_with("--") => (Some(PathBuf::from(arg)), &a[2..]),
+        _ => (None, &a[1..]),
+    };
 
-    let filename = &a[1];
-    log::info!("Emulating {filename}");
+    // Options are applied once the configuration file is read, so they
+    // override it.
+    let mut options = vec![];
+    let mut config_file = None;
+    let mut database_file = None;
+    let mut gdb = None;
+    let mut tracer = None;
+    let mut wav = None;
+    let mut video_path = None;
+    let mut replay = None;
 
-    let pixel_width = 20_i32;
-    let pixel_height = 20_i32;
+    let mut opts = args.iter();
+    while let Some(opt) = opts.next() {
+        let Some(name) = opt.strip_prefix("--") else {
+            fail(&format!("Unknown argument {opt}, see --help"));
+        };
+        if FLAGS.contains(&name) {
+            options.push((name, "true".to_string()));
+            continue;
+        }
+        let Some(value) = opts.next() else {
+            fail(&format!("{opt} expects a value"));
+        };
+
+        match name {
+            "config" => config_file = Some(PathBuf::from(value)),
+            "database" => database_file = Some(PathBuf::from(value)),
+            "gdb" => {
+                let Ok(port) = value.parse::<u16>() else {
+                    fail("--gdb expects a port number");
+                };
+                match GdbStub::bind(port) {
+                    Ok(stub) => gdb = Some(stub),
+                    Err(e) => fail(&format!("Failed to listen on port {port}: {e}")),
+                }
+            }
+            "trace" => match Tracer::create(Path::new(value)) {
+                Ok(t) => tracer = Some(t),
+                Err(e) => fail(&format!("Failed to create {value}: {e}")),
+            },
+            "wav" => wav = Some(value.clone()),
+            "video" => video_path = Some(value.clone()),
+            "replay" => match KeyScript::load(Path::new(value)) {
+                Ok(script) => replay = Some(Replay::new(script)),
+                Err(e) => fail(&e.to_string()),
+            },
+            _ => options.push((name, value.clone())),
+        }
+    }
 
-    // Use a window of 1280 x 640
-    let screen_width: i32 = RESOLUTION.0 * pixel_width;
-    let screen_height: i32 = RESOLUTION.1 * pixel_height;
+    let database = Database::open(database_file.as_deref()).unwrap_or_else(|e| fail(&e));
+    let mut recent = Recent::load(Path::new(RECENT_FILE)).unwrap_or_else(|e| {
+        log::warn!("Recent ROMs are forgotten: {e}");
+        Recent::new(Path::new(RECENT_FILE))
+    });
+    let mut browser = None;
+    let (mut config, mut keymap, mut chip, rom) = match &rom_path {
+        Some(path) => {
+            log::info!("Emulating {}", path.display());
+            let (config, keymap, chip) =
+                open_rom(path, config_file.as_deref(), &options, &database)
+                    .unwrap_or_else(|e| fail(&e));
+            remember(&mut recent, path);
+            let rom = chip.rom().to_vec();
+            (config, keymap, chip, rom)
+        }
+        None => {
+            let config = load_config(config_file.as_deref(), &options, Path::new(""), None)
+                .unwrap_or_else(|e| fail(&e));
+            let keymap = Keymap::from_option(&config.keymap).unwrap_or_else(|e| fail(&e));
+            browser = Some(Browser::new(Path::new(&config.rom_dir), &recent, &database));
+            (config, keymap, Chip8::default(), vec![])
+        }
+    };
+    chip.set_tracer(tracer);
+    log::info!("Variant {}, {:?}", config.variant, config.quirks());
 
-    let mut chip = Chip8::default();
-    chip.load(filename).unwrap();
-
-    init_window(screen_width, screen_height, "Chip8 emulator".to_string());
-
-    set_target_fps(360);
-
-    // Check key pressed
-    // Original layout
-    //  1	2	3	C
-    //  4	5	6	D
-    //  7	8	9	E
-    //  A	0	B	F
-    let keymap = [
-        keys::KEY_A,
-        keys::KEY_Z,
-        keys::KEY_E,
-        keys::KEY_R,
-        keys::KEY_T,
-        keys::KEY_Q,
-        keys::KEY_S,
-        keys::KEY_D,
-        keys::KEY_F,
-        keys::KEY_G,
-        keys::KEY_H,
-        keys::KEY_U,
-        keys::KEY_J,
-        keys::KEY_I,
-        keys::KEY_K,
-        keys::KEY_O,
-    ];
+    let mut palettes = all_palettes(&config);
+    let mut palette_index = 0;
+
+    let mut beeper = Beeper::new(SAMPLE_RATE);
+    set_tone(&mut beeper, &config);
+
+    // The recorder is created once all options of the tone are known
+    let mut recorder = wav.map(|path| match Recorder::create(Path::new(&path), &beeper) {
+        Ok(r) => r,
+        Err(e) => fail(&format!("Failed to create {path}: {e}")),
+    });
+
+    let mut video =
+        video_path.map(
+            |path| match start_video(Path::new(&path), &chip, &config, &palettes[0]) {
+                Some(v) => v,
+                None => exit(1),
+            },
+        );
+
+    // The window can be resized, the scale only gives its initial size.
+    // With the default scale it is a window of 1280 x 640.
+    let (width, height) = chip.resolution();
+    set_config_flags(FLAG_WINDOW_RESIZABLE);
+    init_window(
+        width as i32 * config.scale,
+        height as i32 * config.scale,
+        "Chip8 emulator".to_string(),
+    );
+    set_window_min_size(width as i32, height as i32);
+    if config.fullscreen {
+        toggle_fullscreen();
+    }
+
+    set_target_fps(FPS);
+
+    // The beeper is pulled by the audio stream so the sound doesn't depend
+    // on the speed of the emulation.
+    init_audio_device();
+    let audio_stream = if is_audio_device_ready() {
+        set_audio_stream_buffer_size_default(AUDIO_BUFFER_SIZE as i32);
+        let stream = load_audio_stream(beeper.sample_rate(), 1);
+        play_audio_stream(stream);
+        Some(stream)
+    } else {
+        log::warn!("No audio device, sound is disabled");
+        None
+    };
+    let mut samples = [0_i16; AUDIO_BUFFER_SIZE];
+
+    let mut paused = config.paused;
+    let mut slow_motion = false;
+    // Failure that stopped the machine and the crash bundle written for it
+    let mut crash = None;
+    let mut bundle: Option<PathBuf> = None;
+    let mut watcher = rom_path
+        .as_deref()
+        .filter(|_| config.watch)
+        .map(|path| RomWatcher::new(path, &rom));
+    let mut snapshot = None;
+    let mut clock = Clock::new();
+    let mut camera = Camera2D::new(Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0), 0.0, 1.0);
+    let mut screen = None;
+    let mut phosphor = Phosphor::new(width, height, config.persistence, config.fade_frames);
 
     while !window_should_close()
     // Detect window close button or ESC key
     {
         // Update keyboard state
-        for (i, k) in keymap.iter().enumerate() {
-            if is_key_pressed(*k) {
-                chip.set_key(i, true)
+        // A CHIP-8 key is down while one of its host keys is down
+        for i in 0..chip.keyboard().len() {
+            let down = keymap.is_down(i) || replay.as_ref().is_some_and(|r| r.is_down(i));
+            if down != chip.keyboard()[i] {
+                chip.set_key(i, down);
             }
-            if is_key_released(*k) {
-                chip.set_key(i, false)
+        }
+
+        if is_key_pressed(BROWSER_KEY) && rom_path.is_some() {
+            browser = match browser {
+                Some(_) => None,
+                None => Some(Browser::new(Path::new(&config.rom_dir), &recent, &database)),
+            };
+        }
+
+        // ROM to switch to, chosen in the browser or dropped on the window
+        let mut selected = None;
+        if is_file_dropped() {
+            selected = dropped_rom();
+        }
+
+        let rows = ((get_screen_height() - 4 * BROWSER_LINE_HEIGHT) / BROWSER_LINE_HEIGHT).max(1);
+        if let Some(b) = browser.as_mut() {
+            let page = rows as isize;
+            let moves = [
+                (keys::KEY_UP, -1),
+                (keys::KEY_DOWN, 1),
+                (keys::KEY_PAGE_UP, -page),
+                (keys::KEY_PAGE_DOWN, page),
+                (keys::KEY_HOME, isize::MIN),
+                (keys::KEY_END, isize::MAX),
+            ];
+            for (key, delta) in moves {
+                if is_key_pressed(key) {
+                    b.move_by(delta);
+                }
+            }
+            if is_key_pressed(keys::KEY_ENTER) {
+                selected = b.selected().map(|entry| entry.path.clone());
             }
         }
 
-        // Step to next instruction
-        // NOTE: Delay and Sound timer are updated by step()
-        if let Err(e) = chip.step() {
-            log::error!("{e}");
-            break;
+        if let Some(path) = selected {
+            match open_rom(&path, config_file.as_deref(), &options, &database) {
+                Ok((new_config, new_keymap, mut new_chip)) => {
+                    log::info!("Emulating {}", path.display());
+                    log::info!("Variant {}, {:?}", new_config.variant, new_config.quirks());
+                    new_chip.set_tracer(chip.take_tracer());
+                    chip = new_chip;
+                    config = new_config;
+                    keymap = new_keymap;
+                    palettes = all_palettes(&config);
+                    palette_index = 0;
+                    set_tone(&mut beeper, &config);
+                    let (width, height) = chip.resolution();
+                    phosphor = Phosphor::new(width, height, config.persistence, config.fade_frames);
+                    watcher = config.watch.then(|| RomWatcher::new(&path, chip.rom()));
+                    if let Some(r) = replay.as_mut() {
+                        r.restart();
+                    }
+                    paused = config.paused;
+                    snapshot = None;
+                    crash = None;
+                    bundle = None;
+                    clock.reset();
+                    remember(&mut recent, &path);
+                    rom_path = Some(path);
+                    browser = None;
+                }
+                Err(e) => log::error!("{e}"),
+            }
         }
 
-        begin_drawing();
-        clear_background(color::BLACK);
+        if let Some(b) = &browser {
+            if let Some(stream) = audio_stream {
+                while is_audio_stream_processed(stream) {
+                    beeper.fill(&mut samples, false);
+                    update_audio_stream(stream, &samples);
+                }
+            }
 
-        let fb = chip.get_framebuffer();
+            begin_drawing();
+            clear_background(color::BLACK);
+            draw_browser(b, rows as usize, rom_path.is_some());
+            end_drawing();
+            continue;
+        }
+        let filename = rom_path
+            .as_deref()
+            .expect("a ROM is loaded when the browser is closed");
+
+        if is_key_pressed(MUTE_KEY) {
+            beeper.muted = !beeper.muted;
+            log::info!("Sound is {}", if beeper.muted { "muted" } else { "on" });
+        }
+
+        if is_key_pressed(PAUSE_KEY) {
+            paused = !paused;
+            log::info!("Emulation is {}", if paused { "paused" } else { "resumed" });
+        }
 
-        for (i, byte) in fb.iter().enumerate() {
-            let pw = pixel_width;
-            let ph = pixel_height;
+        if is_key_pressed(RECORD_KEY) {
+            video = match video.take() {
+                Some(v) => {
+                    stop_video(v);
+                    None
+                }
+                None => start_video(
+                    &timestamp::file_name(Path::new("."), "chip8", "gif"),
+                    &chip,
+                    &config,
+                    &palettes[palette_index],
+                ),
+            };
+        }
 
-            let x: i32 = ((i as i32 * 8) % RESOLUTION.0) * pw;
-            let y: i32 = (i as i32 / 8) * ph;
+        if is_key_pressed(SCREENSHOT_KEY) {
+            screenshot(&chip, &config, &palettes[palette_index]);
+        }
 
-            // We draw a 20x20 rectangle for each bit set to 1
-            if byte & 0x80 == 0x80 {
-                draw_rectangle(x, y, pw, ph, color::GREEN);
+        if is_key_pressed(FULLSCREEN_KEY) {
+            toggle_fullscreen();
+            log::info!(
+                "Fullscreen is {}",
+                if is_window_fullscreen() { "on" } else { "off" }
+            );
+        }
+
+        let wheel = get_mouse_wheel_move();
+        if wheel != 0.0 {
+            zoom(&mut camera, wheel, get_mouse_position());
+        }
+
+        if is_key_pressed(SAVE_STATE_KEY) {
+            snapshot = Some(chip.snapshot());
+            log::info!("State saved");
+        }
+
+        if let Some(bytes) = watcher.as_mut().and_then(RomWatcher::poll) {
+            match reload(&mut chip, &bytes, snapshot.as_ref(), &mut replay) {
+                Ok(()) => {
+                    log::info!("{} changed, it is reloaded", filename.display());
+                    phosphor.update(chip.get_framebuffer());
+                    crash = None;
+                }
+                Err(e) => log::error!("Failed to load {}: {e}", filename.display()),
             }
-            if byte & 0x40 == 0x40 {
-                draw_rectangle(x + pw, y, pw, ph, color::GREEN);
+        }
+
+        if is_key_pressed(PALETTE_KEY) {
+            palette_index = (palette_index + 1) % palettes.len();
+            log::info!("Palette {}", palettes[palette_index]);
+        }
+
+        if is_key_pressed(SLOW_MOTION_KEY) {
+            slow_motion = !slow_motion;
+            log::info!("Slow motion is {}", if slow_motion { "on" } else { "off" });
+        }
+        let fast_forward = is_key_down(FAST_FORWARD_KEY);
+
+        // The resolution changes when a ROM switches to the high resolution
+        let (width, height) = chip.resolution();
+        if phosphor.resolution() != (width, height) {
+            log::info!("Resolution is {width}x{height}");
+            phosphor = Phosphor::new(width, height, config.persistence, config.fade_frames);
+            if let Some(v) = video.take() {
+                log::warn!("Video recording is stopped by the change of resolution");
+                stop_video(v);
             }
-            if byte & 0x20 == 0x20 {
-                draw_rectangle(x + 2 * pw, y, pw, ph, color::GREEN);
+        }
+
+        // Run the ticks that fit in the time spent since the last frame
+        let elapsed = get_frame_time() as f64;
+        let mut ticks = 0;
+        let mut ran = 0;
+        let mut result = Ok(());
+        if is_key_pressed(RESET_KEY) {
+            if is_key_down(keys::KEY_LEFT_SHIFT) || is_key_down(keys::KEY_RIGHT_SHIFT) {
+                log::info!("Hard reset");
+                chip.hard_reset();
+            } else {
+                log::info!("Reset");
+                chip.reset();
             }
-            if byte & 0x10 == 0x10 {
-                draw_rectangle(x + 3 * pw, y, pw, ph, color::GREEN);
+            phosphor.update(chip.get_framebuffer());
+            crash = None;
+        } else if is_key_pressed(LOAD_STATE_KEY) {
+            match &snapshot {
+                Some(s) => {
+                    log::info!("State restored");
+                    chip.restore(s);
+                    phosphor.update(chip.get_framebuffer());
+                    crash = None;
+                }
+                None => log::warn!("No state is saved, press F2 to save one"),
             }
-            if byte & 0x8 == 0x8 {
-                draw_rectangle(x + 4 * pw, y, pw, ph, color::GREEN);
+        } else if crash.is_some() {
+            // The machine is frozen until the user chooses what to do
+            clock.reset();
+            if is_key_pressed(RELOAD_KEY) {
+                let reloaded = fs::read(filename)
+                    .map(|bytes| reload(&mut chip, &bytes, snapshot.as_ref(), &mut replay));
+                match reloaded {
+                    Ok(Ok(())) => {
+                        log::info!("{} is reloaded", filename.display());
+                        phosphor.update(chip.get_framebuffer());
+                        crash = None;
+                    }
+                    Ok(Err(e)) => log::error!("Failed to load {}: {e}", filename.display()),
+                    Err(e) => log::error!("Cannot read {}: {e}", filename.display()),
+                }
+            } else if is_key_pressed(DEBUGGER_KEY) {
+                if gdb.is_none() {
+                    match GdbStub::bind(DEBUGGER_PORT) {
+                        Ok(stub) => gdb = Some(stub),
+                        Err(e) => log::error!("Failed to listen on port {DEBUGGER_PORT}: {e}"),
+                    }
+                }
+                // The debugger shows the machine as it is after the error
+                crash = None;
             }
-            if byte & 0x4 == 0x4 {
-                draw_rectangle(x + 5 * pw, y, pw, ph, color::GREEN);
+        } else if gdb.is_some() {
+            // The debugger runs a tick per frame
+            clock.reset();
+            ticks = 1;
+        } else if paused {
+            clock.reset();
+            if is_key_pressed(FRAME_KEY) {
+                ticks = 1;
+            } else if is_key_pressed(STEP_KEY) {
+                result = crash::catch(|| chip.step());
+                phosphor.update(chip.get_framebuffer());
             }
-            if byte & 0x2 == 0x2 {
-                draw_rectangle(x + 6 * pw, y, pw, ph, color::GREEN);
+        } else if fast_forward && config.fast_forward == 0.0 {
+            // As many ticks as possible without slowing down the window
+            let start = Instant::now();
+            while result.is_ok() && start.elapsed() < UNCAPPED_DURATION {
+                result = crash::catch(|| {
+                    tick(
+                        &mut chip,
+                        &config,
+                        None,
+                        &mut replay,
+                        &mut phosphor,
+                        &mut recorder,
+                        &mut video,
+                    )
+                });
+                ran += 1;
             }
-            if byte & 0x1 == 0x1 {
-                draw_rectangle(x + 7 * pw, y, pw, ph, color::GREEN);
+        } else {
+            let speed = match (fast_forward, slow_motion) {
+                (true, _) => config.fast_forward,
+                (false, true) => config.slow_motion,
+                (false, false) => 1.0,
+            };
+            ticks = clock.advance(elapsed, speed);
+        }
+        while result.is_ok() && ran < ticks {
+            result = crash::catch(|| {
+                tick(
+                    &mut chip,
+                    &config,
+                    gdb.as_mut(),
+                    &mut replay,
+                    &mut phosphor,
+                    &mut recorder,
+                    &mut video,
+                )
+            });
+            ran += 1;
+        }
+        if let Err(failure) = result {
+            log::error!("{failure}");
+            bundle = match crash::write_bundle(
+                Path::new("."),
+                &chip,
+                &failure,
+                filename,
+                chip.rom(),
+                &config.to_toml(),
+            ) {
+                Ok(path) => {
+                    log::info!("Crash bundle written in {}", path.display());
+                    Some(path)
+                }
+                Err(e) => {
+                    log::error!("Failed to write the crash bundle: {e}");
+                    None
+                }
+            };
+            crash = Some(failure);
+        }
+
+        if let Some(stream) = audio_stream {
+            while is_audio_stream_processed(stream) {
+                beeper.fill(&mut samples, chip.sound_timer() > 0);
+                update_audio_stream(stream, &samples);
             }
         }
 
+        let palette = &palettes[palette_index];
+        let window = (get_screen_width(), get_screen_height());
+        let viewport = Viewport::letterbox(window, (width, height), config.integer_scale);
+
+        // Borders of the letterbox are black
+        begin_drawing();
+        clear_background(color::BLACK);
+        begin_mode_2d(camera);
+        draw_display(&mut screen, &phosphor, palette, &viewport, &config.overlays);
+        end_mode_2d();
+
+        let speed = if paused {
+            Some("Paused".to_string())
+        } else if fast_forward && config.fast_forward == 0.0 {
+            Some(format!(">> x{:.1}", ran as f64 * TICK / elapsed.max(TICK)))
+        } else if fast_forward {
+            Some(format!(">> x{}", config.fast_forward))
+        } else if slow_motion {
+            Some(format!("Slow motion x{}", config.slow_motion))
+        } else {
+            None
+        };
+        if let Some(failure) = &crash {
+            draw_crash(&chip, failure, bundle.as_deref());
+        } else if let Some(text) = speed {
+            draw_rectangle(
+                0,
+                0,
+                12 * text.len() as i32 + 20,
+                40,
+                color::Color::new(0, 0, 0, 160),
+            );
+            draw_text(text, 10, 10, 20, color::WHITE);
+        }
         end_drawing();
     }
 
     // De-Initialization
+    chip.set_tracer(None); // Flush the trace
+    if let Some(v) = video {
+        stop_video(v);
+    }
+    if let Some(Err(e)) = recorder.map(Recorder::finish) {
+        log::error!("Failed to finish the audio recording: {e}");
+    }
+    if let Some(texture) = screen {
+        unload_texture(texture);
+    }
+    if let Some(stream) = audio_stream {
+        unload_audio_stream(stream);
+    }
+    close_audio_device();
     close_window(); // Close window and OpenGL context
 }
+
+/// Read the configuration of `rom`: the variant given by its extension, the
+/// settings of the database, then the configuration file and the options of
+/// the command line
+fn load_config(
+    config_file: Option<&Path>,
+    options: &[(&str, String)],
+    rom: &Path,
+    info: Option<&RomInfo>,
+) -> Result<Config, String> {
+    let mut config = Config {
+        variant: Variant::from_path(rom),
+        ..Config::default()
+    };
+    if let Some(info) = info {
+        info.apply(&mut config);
+    }
+    match config_file {
+        Some(path) => config.load(path, rom)?,
+        None if Path::new(CONFIG_FILE).is_file() => config.load(Path::new(CONFIG_FILE), rom)?,
+        None => {}
+    }
+    for (name, value) in options {
+        config.set(name, value).map_err(|e| format!("--{e}"))?;
+    }
+    Ok(config)
+}
+
+/// Read the ROM at `path` and create a machine that runs it with its
+/// configuration and its keymap
+fn open_rom(
+    path: &Path,
+    config_file: Option<&Path>,
+    options: &[(&str, String)],
+    database: &Database,
+) -> Result<(Config, Keymap, Chip8), String> {
+    let rom = fs::read(path).map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+    let info = database.get(&rom);
+    if let Some(info) = info {
+        match &info.author {
+            Some(author) => log::info!("{} by {author}", info.title),
+            None => log::info!("{}", info.title),
+        }
+        if !info.keys.is_empty() {
+            log::info!("Keys: {}", info.key_hints());
+        }
+    }
+    let config = load_config(config_file, options, path, info)?;
+    let keymap = Keymap::from_option(&config.keymap)?;
+    let chip =
+        new_chip(&rom, &config).map_err(|e| format!("Failed to load {}: {e}", path.display()))?;
+    Ok((config, keymap, chip))
+}
+
+/// Return the ROM of the first file dropped on the window that can be loaded
+fn dropped_rom() -> Option<PathBuf> {
+    let files = load_dropped_files();
+    let paths = files.paths();
+    unload_dropped_files(files);
+
+    for path in paths {
+        match library::rom_for(&path) {
+            Ok(rom) => return Some(rom),
+            Err(e) => log::warn!("{e}"),
+        }
+    }
+    None
+}
+
+/// Put `rom` at the top of the recent ROMs and save them
+fn remember(recent: &mut Recent, rom: &Path) {
+    recent.add(rom, timestamp::now());
+    if let Err(e) = recent.save() {
+        log::warn!("Failed to save the recent ROMs in {RECENT_FILE}: {e}");
+    }
+}
+
+/// Return the palette of `config` followed by the presets
+fn all_palettes(config: &Config) -> Vec<Palette> {
+    let mut palettes = vec![config.palette()];
+    for name in palette::PRESETS {
+        let preset = Palette::preset(name).unwrap();
+        if preset != palettes[0] {
+            palettes.push(preset);
+        }
+    }
+    palettes
+}
+
+/// Set the tone of the beep from `config`
+fn set_tone(beeper: &mut Beeper, config: &Config) {
+    beeper.frequency = config.tone;
+    beeper.volume = config.volume as f32 / 100.0;
+    beeper.waveform = config.waveform;
+}
+
+/// Draw the ROM browser, `rows` ROMs at once. It can be closed when a ROM is
+/// running.
+fn draw_browser(browser: &Browser, rows: usize, closable: bool) {
+    let line = |n: i32| BROWSER_LINE_HEIGHT * n + 10;
+    draw_text(
+        format!("ROMs of {}", browser.dir().display()),
+        10,
+        line(0),
+        20,
+        color::LIGHTGRAY,
+    );
+
+    if browser.entries().is_empty() {
+        draw_text(
+            "No ROM found, drop a ROM on the window or set --rom-dir".to_string(),
+            10,
+            line(1),
+            20,
+            color::WHITE,
+        );
+    }
+    for (n, i) in browser.visible(rows).enumerate() {
+        let entry = &browser.entries()[i];
+        let y = line(n as i32 + 1);
+        if i == browser.selected_index() {
+            let width = get_screen_width();
+            draw_rectangle(0, y - 2, width, BROWSER_LINE_HEIGHT, color::DARKGRAY);
+        }
+        let played = entry
+            .played
+            .map_or("never".to_string(), timestamp::date_time);
+        draw_text(entry.title(), 20, y, 20, color::WHITE);
+        draw_text(format!("{} B", entry.size), 420, y, 20, color::WHITE);
+        draw_text(entry.variant.to_string(), 540, y, 20, color::WHITE);
+        draw_text(played, 660, y, 20, color::WHITE);
+    }
+
+    let mut help = "Up/Down select, Enter play".to_string();
+    if closable {
+        help.push_str(", F1 back to the game");
+    }
+    help.push_str(", Esc quit");
+    let bottom = get_screen_height() - BROWSER_LINE_HEIGHT - 6;
+    draw_text(help, 10, bottom, 20, color::LIGHTGRAY);
+
+    // Keys of the selected ROM, from the database
+    if let Some(info) = browser.selected().and_then(|entry| entry.info.as_ref()) {
+        if !info.keys.is_empty() {
+            let hints = format!("Keys: {}", info.key_hints());
+            draw_text(
+                hints,
+                10,
+                bottom - BROWSER_LINE_HEIGHT,
+                20,
+                color::LIGHTGRAY,
+            );
+        }
+    }
+}
+
+/// Create a machine that runs `rom` with the quirks and the seed of `config`
+fn new_chip(rom: &[u8], config: &Config) -> Result<Chip8, Chip8Error> {
+    let mut chip = Chip8::default();
+    chip.load_bytes(rom)?;
+    chip.set_quirks(config.quirks());
+    chip.set_history_size(config.history);
+    if let Some(seed) = config.seed {
+        chip.set_seed(seed);
+    }
+    Ok(chip)
+}
+
+/// Load a new version of the ROM in `chip`, then restore `snapshot` with it
+/// and play `replay` again
+fn reload(
+    chip: &mut Chip8,
+    rom: &[u8],
+    snapshot: Option<&Snapshot>,
+    replay: &mut Option<Replay>,
+) -> Result<(), Chip8Error> {
+    chip.load_bytes(rom)?;
+    if let Some(s) = snapshot {
+        chip.restore(s);
+        chip.reload_rom();
+    }
+    if let Some(r) = replay {
+        r.restart();
+    }
+    Ok(())
+}
+
+/// Draw the crash report over the screen with the keys to continue
+fn draw_crash(chip: &Chip8, failure: &Failure, bundle: Option<&Path>) {
+    let (width, height) = (get_screen_width(), get_screen_height());
+    draw_rectangle(0, 0, width, height, color::Color::new(0, 0, 0, 200));
+
+    let mut lines = crash::report(chip, failure, CRASH_HISTORY_LINES);
+    lines.push(String::new());
+    if let Some(path) = bundle {
+        lines.push(format!("Crash bundle written in {}", path.display()));
+    }
+    lines.push(format!(
+        "F5 reset (Shift+F5 hard reset), F6 reload the ROM, F7 debug with gdb on port {DEBUGGER_PORT}, Esc quit"
+    ));
+    for (n, line) in lines.into_iter().enumerate() {
+        let c = if n == 0 { color::RED } else { color::WHITE };
+        draw_text(line, 10, 10 + 22 * n as i32, 20, c);
+    }
+}
+
+/// Run a tick: the instructions of a frame then the timers, or let `gdb` run
+/// them. The recordings follow the emulated time, not the real one, so they
+/// wait while the debugger halts the machine.
+fn tick(
+    chip: &mut Chip8,
+    config: &Config,
+    gdb: Option<&mut GdbStub>,
+    replay: &mut Option<Replay>,
+    phosphor: &mut Phosphor,
+    recorder: &mut Option<Recorder>,
+    video: &mut Option<VideoRecorder>,
+) -> Result<(), Chip8Error> {
+    if let Some(r) = replay {
+        if !gdb.as_ref().is_some_and(|stub| stub.is_halted()) {
+            r.advance(chip);
+        }
+    }
+    let ran = match gdb {
+        // Errors are reported to the debugger instead of stopping the
+        // emulator
+        Some(stub) => stub.poll(chip, config.ipf),
+        None => {
+            chip.run_frame(config.ipf)?;
+            true
+        }
+    };
+    // The debugger can also step while the machine is halted
+    phosphor.update(chip.get_framebuffer());
+    if !ran {
+        return Ok(());
+    }
+
+    if let Some(r) = recorder {
+        if let Err(e) = r.advance(TICK, chip.sound_timer() > 0) {
+            log::error!("Audio recording is stopped: {e}");
+            *recorder = None;
+        }
+    }
+    if let Some(v) = video {
+        if let Err(e) = v.advance(TICK, chip.get_framebuffer()) {
+            log::error!("Video recording is stopped: {e}");
+            *video = None;
+        }
+    }
+    Ok(())
+}
+
+/// Draw the screen in the viewport. It is drawn in a texture, where pixels
+/// are larger than 1 only to draw the overlays.
+fn draw_display(
+    screen: &mut Option<Texture2D>,
+    phosphor: &Phosphor,
+    palette: &Palette,
+    viewport: &Viewport,
+    overlays: &Overlays,
+) {
+    let scale = if overlays.is_empty() {
+        1
+    } else {
+        (viewport.pixel_size.round() as usize).clamp(1, MAX_TEXTURE_SCALE)
+    };
+    let (width, height) = phosphor.resolution();
+    let (width, height) = ((width * scale) as i32, (height * scale) as i32);
+    let pixels = phosphor.to_rgba(palette, scale, overlays);
+
+    let texture = match *screen {
+        Some(t) if t.width == width && t.height == height => {
+            update_texture(t, &pixels);
+            t
+        }
+        _ => {
+            if let Some(t) = screen.take() {
+                unload_texture(t);
+            }
+            let t = load_texture_from_rgba(&pixels, width, height);
+            set_texture_filter(t, TEXTURE_FILTER_POINT);
+            *screen = Some(t);
+            t
+        }
+    };
+
+    let (display_width, display_height) = viewport.size();
+    let source = Rectangle::new(0.0, 0.0, width as f32, height as f32);
+    let dest = Rectangle::new(viewport.x, viewport.y, display_width, display_height);
+    draw_texture_pro(texture, source, dest, color::WHITE);
+}
+
+/// Zoom in or out by `wheel` steps, the point under the mouse stays in place
+fn zoom(camera: &mut Camera2D, wheel: f32, mouse: Vector2) {
+    // Point of the window under the mouse before zooming
+    let target = Vector2::new(
+        camera.target.x + (mouse.x - camera.offset.x) / camera.zoom,
+        camera.target.y + (mouse.y - camera.offset.y) / camera.zoom,
+    );
+    camera.zoom = (camera.zoom * (1.0 + ZOOM_STEP * wheel)).clamp(1.0, MAX_ZOOM);
+    if camera.zoom == 1.0 {
+        // Back to the whole window
+        *camera = Camera2D::new(Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0), 0.0, 1.0);
+    } else {
+        camera.offset = mouse;
+        camera.target = target;
+    }
+}
+
+/// Start to record the screen in `path`
+fn start_video(
+    path: &Path,
+    chip: &Chip8,
+    config: &Config,
+    palette: &Palette,
+) -> Option<VideoRecorder> {
+    let (width, height) = chip.resolution();
+    match VideoRecorder::create(
+        path,
+        width,
+        height,
+        config.video_scale,
+        palette.foreground(),
+        palette.background(),
+    ) {
+        Ok(v) => {
+            log::info!("Recording the screen in {}", path.display());
+            Some(v)
+        }
+        Err(e) => {
+            log::error!("Failed to create {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Save the screen in a PNG file named after the current time
+fn screenshot(chip: &Chip8, config: &Config, palette: &Palette) {
+    let (width, height) = chip.resolution();
+    let scale = config.screenshot_scale;
+    let rgb = image::to_rgb(
+        chip.get_framebuffer(),
+        width,
+        height,
+        scale,
+        palette.foreground(),
+        palette.background(),
+    );
+    let png = image::to_png(&rgb, width * scale, height * scale);
+
+    let path = timestamp::file_name(Path::new("."), "chip8", "png");
+    match std::fs::write(&path, png) {
+        Ok(()) => log::info!("Screenshot saved in {}", path.display()),
+        Err(e) => log::error!("Failed to write {}: {e}", path.display()),
+    }
+}
+
+fn stop_video(video: VideoRecorder) {
+    let frames = video.frames();
+    match video.finish() {
+        Ok(()) => log::info!("Recording is stopped after {frames} frames"),
+        Err(e) => log::error!("Failed to finish the video: {e}"),
+    }
+}
@@ -0,0 +1,177 @@
+//! ## Palettes
+//! A palette gives the colour of each value of a pixel: 0 is the
+//! background and 1 the foreground. XO-CHIP draws on two planes so its
+//! pixels have 4 values, 2 for the second plane alone and 3 for both planes.
+//!
+//! A user palette is written as 2 to 4 colours separated by commas, like
+//! `#000000,#FFB000`. Missing colours are the foreground.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::parse_color;
+
+/// Names of the presets accepted by [`Palette::preset`]
+pub const PRESETS: [&str; 5] = ["classic", "amber", "green", "lcd", "high-contrast"];
+
+/// Maximum number of colours of a palette
+pub const MAX_COLORS: usize = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    /// Name of the preset, or `custom`
+    name: String,
+    colors: [[u8; 3]; MAX_COLORS],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::classic()
+    }
+}
+
+impl Palette {
+    /// Build a palette from 2 to 4 colours, the first one is the background
+    pub fn new(name: &str, colors: &[[u8; 3]]) -> Result<Palette, String> {
+        if !(2..=MAX_COLORS).contains(&colors.len()) {
+            return Err(format!(
+                "expected 2 to {MAX_COLORS} colours, got {}",
+                colors.len()
+            ));
+        }
+        let mut all = [colors[1]; MAX_COLORS];
+        all[..colors.len()].copy_from_slice(colors);
+        Ok(Palette {
+            name: name.to_string(),
+            colors: all,
+        })
+    }
+
+    fn from_colors(name: &str, colors: [[u8; 3]; MAX_COLORS]) -> Palette {
+        Palette {
+            name: name.to_string(),
+            colors,
+        }
+    }
+
+    /// White on black
+    pub fn classic() -> Palette {
+        Palette::from_colors("classic", [[0, 0, 0], [255; 3], [170; 3], [85; 3]])
+    }
+
+    /// Amber monochrome monitor
+    pub fn amber() -> Palette {
+        Palette::from_colors(
+            "amber",
+            [
+                [0x1A, 0x0F, 0x00],
+                [0xFF, 0xB0, 0x00],
+                [0xB3, 0x6B, 0x00],
+                [0xFF, 0xD8, 0x80],
+            ],
+        )
+    }
+
+    /// Green phosphor monitor
+    pub fn green() -> Palette {
+        Palette::from_colors(
+            "green",
+            [
+                [0x05, 0x14, 0x05],
+                [0x33, 0xFF, 0x33],
+                [0x1A, 0x99, 0x1A],
+                [0xA0, 0xFF, 0xA0],
+            ],
+        )
+    }
+
+    /// Dark pixels on the greenish screen of old handhelds
+    pub fn lcd() -> Palette {
+        Palette::from_colors(
+            "lcd",
+            [
+                [0x9B, 0xBC, 0x0F],
+                [0x0F, 0x38, 0x0F],
+                [0x8B, 0xAC, 0x0F],
+                [0x30, 0x62, 0x30],
+            ],
+        )
+    }
+
+    /// Yellow on black, for low vision
+    pub fn high_contrast() -> Palette {
+        Palette::from_colors(
+            "high-contrast",
+            [
+                [0, 0, 0],
+                [0xFF, 0xFF, 0x00],
+                [0x00, 0xFF, 0xFF],
+                [0xFF, 0xFF, 0xFF],
+            ],
+        )
+    }
+
+    pub fn preset(name: &str) -> Option<Palette> {
+        match name {
+            "classic" => Some(Palette::classic()),
+            "amber" => Some(Palette::amber()),
+            "green" => Some(Palette::green()),
+            "lcd" => Some(Palette::lcd()),
+            "high-contrast" => Some(Palette::high_contrast()),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the colour of the pixel value `value`, from 0 to 3
+    pub fn color(&self, value: usize) -> [u8; 3] {
+        self.colors[value.min(MAX_COLORS - 1)]
+    }
+
+    pub fn background(&self) -> [u8; 3] {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> [u8; 3] {
+        self.colors[1]
+    }
+
+    pub fn set_background(&mut self, color: [u8; 3]) {
+        self.colors[0] = color;
+    }
+
+    pub fn set_foreground(&mut self, color: [u8; 3]) {
+        self.colors[1] = color;
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    /// Parse the name of a preset or a list of colours
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(palette) = Palette::preset(s) {
+            return Ok(palette);
+        }
+        if !s.contains(['#', ',']) {
+            return Err(format!(
+                "unknown palette '{s}' ({} or colours like #000000,#FFB000)",
+                PRESETS.join(", ")
+            ));
+        }
+        let colors = s
+            .split(',')
+            .map(|c| parse_color(c.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Palette::new("custom", &colors)
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
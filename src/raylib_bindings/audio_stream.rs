@@ -0,0 +1,11 @@
+use std::ffi::c_void;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AudioStream {
+    buffer: *mut c_void,
+    processor: *mut c_void,
+    pub sample_rate: u32,
+    pub sample_size: u32,
+    pub channels: u32,
+}
@@ -3,7 +3,7 @@ use super::vector2::Vector2;
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct Camera2D {
-    offset: Vector2,
+    pub offset: Vector2,
     pub target: Vector2,
     rotation: f32,
     pub zoom: f32,
@@ -8,9 +8,20 @@ pub struct Color {
 }
 
 impl Color {
-    const fn new(r: u8, g: u8, b: u8, a: u8) -> Color {
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Color {
         Color { r, g, b, a }
     }
+
+    /// An opaque colour
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color::new(r, g, b, 255)
+    }
+}
+
+impl From<[u8; 3]> for Color {
+    fn from([r, g, b]: [u8; 3]) -> Color {
+        Color::rgb(r, g, b)
+    }
 }
 
 pub const BLACK: Color = Color::new(0, 0, 0, 255);
@@ -0,0 +1,26 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::PathBuf;
+
+/// Paths of files, like the ones dropped on the window
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct FilePathList {
+    capacity: u32,
+    count: u32,
+    paths: *mut *mut c_char,
+}
+
+impl FilePathList {
+    /// Return a copy of the paths, they are valid until the list is unloaded
+    pub fn paths(&self) -> Vec<PathBuf> {
+        if self.paths.is_null() {
+            return vec![];
+        }
+        let paths = unsafe { std::slice::from_raw_parts(self.paths, self.count as usize) };
+        paths
+            .iter()
+            .map(|p| PathBuf::from(unsafe { CStr::from_ptr(*p) }.to_string_lossy().to_string()))
+            .collect()
+    }
+}
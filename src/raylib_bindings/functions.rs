@@ -0,0 +1,301 @@
+use std::{
+    ffi::{c_float, c_void, CString},
+    os::raw::{c_char, c_int, c_uint},
+};
+
+use super::audio_stream::AudioStream;
+use super::camera2d::Camera2D;
+use super::color::Color;
+use super::file_path_list::FilePathList;
+use super::rectangle::Rectangle;
+use super::texture::{Image, Texture2D};
+use super::vector2::Vector2;
+
+/// Flag of [`set_config_flags`] to let the user resize the window
+pub const FLAG_WINDOW_RESIZABLE: u32 = 0x0000_0004;
+
+extern "C" {
+    fn BeginDrawing() -> c_void;
+    fn BeginMode2D(camera: Camera2D) -> c_void;
+    fn ClearBackground(c: Color) -> c_void;
+    fn CloseAudioDevice() -> c_void;
+    fn CloseWindow() -> c_void;
+    fn DrawLine(start_x: i32, start_y: i32, end_x: i32, end_y: i32, c: Color) -> c_void;
+    fn DrawText(text: *const c_char, x: c_int, y: c_int, fs: c_int, c: Color) -> c_void;
+    fn DrawRectangle(x: c_int, y: c_int, w: c_int, h: c_int, color: Color) -> c_void;
+    fn DrawRectangleRec(rec: Rectangle, color: Color) -> c_void;
+    fn DrawTexturePro(
+        texture: Texture2D,
+        source: Rectangle,
+        dest: Rectangle,
+        origin: Vector2,
+        rotation: c_float,
+        tint: Color,
+    ) -> c_void;
+    fn EndDrawing() -> c_void;
+    fn EndMode2D() -> c_void;
+    fn GetFrameTime() -> c_float;
+    fn GetGamepadAxisMovement(gamepad: c_int, axis: c_int) -> c_float;
+    fn GetMousePosition() -> Vector2;
+    fn GetMouseWheelMove() -> c_float;
+    fn GetScreenHeight() -> c_int;
+    fn GetScreenWidth() -> c_int;
+    fn InitAudioDevice() -> c_void;
+    fn InitWindow(w: c_int, h: c_int, title: *const c_char) -> c_void;
+    fn IsAudioDeviceReady() -> bool;
+    fn IsAudioStreamProcessed(stream: AudioStream) -> bool;
+    fn IsFileDropped() -> bool;
+    fn IsGamepadAvailable(gamepad: c_int) -> bool;
+    fn IsGamepadButtonDown(gamepad: c_int, button: c_int) -> bool;
+    fn IsKeyPressed(k: c_int) -> c_int;
+    fn IsKeyReleased(k: c_int) -> c_int;
+    fn IsKeyDown(k: c_int) -> c_int;
+    fn IsWindowFullscreen() -> bool;
+    fn LoadTextureFromImage(image: Image) -> Texture2D;
+    fn LoadAudioStream(sample_rate: c_uint, sample_size: c_uint, channels: c_uint) -> AudioStream;
+    fn LoadDroppedFiles() -> FilePathList;
+    fn PlayAudioStream(stream: AudioStream) -> c_void;
+    fn SetAudioStreamBufferSizeDefault(size: c_int) -> c_void;
+    fn SetConfigFlags(flags: c_uint) -> c_void;
+    fn SetTargetFPS(fps: c_int) -> c_void;
+    fn SetTextureFilter(texture: Texture2D, filter: c_int) -> c_void;
+    fn SetWindowMinSize(width: c_int, height: c_int) -> c_void;
+    fn ToggleFullscreen() -> c_void;
+    fn UnloadAudioStream(stream: AudioStream) -> c_void;
+    fn UnloadDroppedFiles(files: FilePathList) -> c_void;
+    fn UnloadTexture(texture: Texture2D) -> c_void;
+    fn UpdateAudioStream(stream: AudioStream, data: *const c_void, frame_count: c_int) -> c_void;
+    fn UpdateTexture(texture: Texture2D, pixels: *const c_void) -> c_void;
+    fn WindowShouldClose() -> c_int;
+}
+
+pub fn begin_drawing() {
+    unsafe { BeginDrawing() };
+}
+
+pub fn begin_mode_2d(camera: Camera2D) {
+    unsafe { BeginMode2D(camera) };
+}
+
+pub fn clear_background(color: Color) {
+    unsafe { ClearBackground(color) };
+}
+
+pub fn close_audio_device() {
+    unsafe { CloseAudioDevice() };
+}
+
+pub fn close_window() {
+    unsafe { CloseWindow() };
+}
+
+pub fn draw_line(start_x: i32, start_y: i32, end_x: i32, end_y: i32, color: Color) {
+    unsafe {
+        DrawLine(
+            start_x as c_int,
+            start_y as c_int,
+            end_x as c_int,
+            end_y as c_int,
+            color,
+        )
+    };
+}
+
+pub fn draw_rectangle(x: i32, y: i32, w: i32, h: i32, color: Color) {
+    unsafe { DrawRectangle(x as c_int, y as c_int, w as c_int, h as c_int, color) };
+}
+
+pub fn draw_rectangle_rec(rec: Rectangle, color: Color) {
+    unsafe { DrawRectangleRec(rec, color) };
+}
+
+/// Draw the `source` part of `texture` in the `dest` part of the window
+pub fn draw_texture_pro(texture: Texture2D, source: Rectangle, dest: Rectangle, tint: Color) {
+    unsafe { DrawTexturePro(texture, source, dest, Vector2::new(0.0, 0.0), 0.0, tint) };
+}
+
+pub fn draw_text(text: String, pos_x: i32, pos_y: i32, font_size: i32, color: Color) {
+    let c_text = CString::new(text).unwrap();
+    unsafe {
+        DrawText(
+            c_text.as_ptr() as *const c_char,
+            pos_x as c_int,
+            pos_y as c_int,
+            font_size as c_int,
+            color,
+        )
+    };
+}
+
+pub fn end_drawing() {
+    unsafe { EndDrawing() };
+}
+
+pub fn end_mode_2d() {
+    unsafe { EndMode2D() };
+}
+
+/// Return the duration of the last frame in seconds
+pub fn get_frame_time() -> f32 {
+    unsafe { GetFrameTime() }
+}
+
+/// Return the position of `axis` of `gamepad`, from -1 to 1
+pub fn get_gamepad_axis_movement(gamepad: i32, axis: i32) -> f32 {
+    unsafe { GetGamepadAxisMovement(gamepad as c_int, axis as c_int) }
+}
+
+pub fn get_mouse_position() -> Vector2 {
+    unsafe { GetMousePosition() }
+}
+
+pub fn get_mouse_wheel_move() -> f32 {
+    let v = unsafe { GetMouseWheelMove() };
+    v as f32
+}
+
+/// Return the height of the window
+pub fn get_screen_height() -> i32 {
+    unsafe { GetScreenHeight() }
+}
+
+/// Return the width of the window
+pub fn get_screen_width() -> i32 {
+    unsafe { GetScreenWidth() }
+}
+
+pub fn init_audio_device() {
+    unsafe { InitAudioDevice() };
+}
+
+pub fn init_window(width: i32, height: i32, title: String) {
+    let c_title = CString::new(title).unwrap();
+    unsafe {
+        InitWindow(
+            width as c_int,
+            height as c_int,
+            c_title.as_ptr() as *const c_char,
+        )
+    };
+}
+
+pub fn is_audio_device_ready() -> bool {
+    unsafe { IsAudioDeviceReady() }
+}
+
+pub fn is_audio_stream_processed(stream: AudioStream) -> bool {
+    unsafe { IsAudioStreamProcessed(stream) }
+}
+
+/// Return true if files were dropped on the window since the last frame
+pub fn is_file_dropped() -> bool {
+    unsafe { IsFileDropped() }
+}
+
+pub fn is_gamepad_available(gamepad: i32) -> bool {
+    unsafe { IsGamepadAvailable(gamepad as c_int) }
+}
+
+pub fn is_gamepad_button_down(gamepad: i32, button: i32) -> bool {
+    unsafe { IsGamepadButtonDown(gamepad as c_int, button as c_int) }
+}
+
+pub fn is_key_pressed(key: i32) -> bool {
+    unsafe { IsKeyPressed(key as c_int) != 0 }
+}
+
+pub fn is_key_released(key: i32) -> bool {
+    unsafe { IsKeyReleased(key as c_int) != 0 }
+}
+
+pub fn is_key_down(key: i32) -> bool {
+    unsafe { IsKeyDown(key as c_int) != 0 }
+}
+
+/// Return true if the window is in fullscreen mode
+pub fn is_window_fullscreen() -> bool {
+    unsafe { IsWindowFullscreen() }
+}
+
+/// Load a stream of 16 bits samples
+pub fn load_audio_stream(sample_rate: u32, channels: u32) -> AudioStream {
+    unsafe { LoadAudioStream(sample_rate as c_uint, 16, channels as c_uint) }
+}
+
+/// Return the files dropped on the window, they must be unloaded with
+/// [`unload_dropped_files`]
+pub fn load_dropped_files() -> FilePathList {
+    unsafe { LoadDroppedFiles() }
+}
+
+/// Create a texture from RGBA `pixels` of `width` x `height`
+pub fn load_texture_from_rgba(pixels: &[u8], width: i32, height: i32) -> Texture2D {
+    unsafe { LoadTextureFromImage(Image::rgba(pixels, width, height)) }
+}
+
+pub fn play_audio_stream(stream: AudioStream) {
+    unsafe { PlayAudioStream(stream) };
+}
+
+/// Set the size in samples of the buffers of streams that will be loaded
+pub fn set_audio_stream_buffer_size_default(size: i32) {
+    unsafe { SetAudioStreamBufferSizeDefault(size as c_int) };
+}
+
+/// Set the `FLAG_*` flags of the window, before it is created
+pub fn set_config_flags(flags: u32) {
+    unsafe { SetConfigFlags(flags as c_uint) };
+}
+
+pub fn set_target_fps(fps: i32) {
+    unsafe { SetTargetFPS(fps as c_int) };
+}
+
+pub fn set_texture_filter(texture: Texture2D, filter: i32) {
+    unsafe { SetTextureFilter(texture, filter as c_int) };
+}
+
+pub fn set_window_min_size(width: i32, height: i32) {
+    unsafe { SetWindowMinSize(width as c_int, height as c_int) };
+}
+
+pub fn toggle_fullscreen() {
+    unsafe { ToggleFullscreen() };
+}
+
+pub fn unload_audio_stream(stream: AudioStream) {
+    unsafe { UnloadAudioStream(stream) };
+}
+
+pub fn unload_dropped_files(files: FilePathList) {
+    unsafe { UnloadDroppedFiles(files) };
+}
+
+pub fn unload_texture(texture: Texture2D) {
+    unsafe { UnloadTexture(texture) };
+}
+
+/// Samples are interleaved if the stream has more than one channel
+pub fn update_audio_stream(stream: AudioStream, samples: &[i16]) {
+    let frame_count = samples.len() as u32 / stream.channels.max(1);
+    unsafe {
+        UpdateAudioStream(
+            stream,
+            samples.as_ptr() as *const c_void,
+            frame_count as c_int,
+        )
+    };
+}
+
+/// Replace the pixels of `texture` by RGBA `pixels` of the same size
+pub fn update_texture(texture: Texture2D, pixels: &[u8]) {
+    assert_eq!(
+        pixels.len(),
+        texture.width as usize * texture.height as usize * 4
+    );
+    unsafe { UpdateTexture(texture, pixels.as_ptr() as *const c_void) };
+}
+
+pub fn window_should_close() -> bool {
+    unsafe { WindowShouldClose() != 0 }
+}
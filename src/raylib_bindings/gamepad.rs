@@ -0,0 +1,75 @@
+//! Gamepad buttons and axes, the same values as `GamepadButton` and
+//! `GamepadAxis` in `raylib.h`
+
+// Buttons, the left face is the D-pad
+pub const BUTTON_UNKNOWN: i32 = 0;
+pub const BUTTON_LEFT_FACE_UP: i32 = 1;
+pub const BUTTON_LEFT_FACE_RIGHT: i32 = 2;
+pub const BUTTON_LEFT_FACE_DOWN: i32 = 3;
+pub const BUTTON_LEFT_FACE_LEFT: i32 = 4;
+pub const BUTTON_RIGHT_FACE_UP: i32 = 5;
+pub const BUTTON_RIGHT_FACE_RIGHT: i32 = 6;
+pub const BUTTON_RIGHT_FACE_DOWN: i32 = 7;
+pub const BUTTON_RIGHT_FACE_LEFT: i32 = 8;
+pub const BUTTON_LEFT_TRIGGER_1: i32 = 9;
+pub const BUTTON_LEFT_TRIGGER_2: i32 = 10;
+pub const BUTTON_RIGHT_TRIGGER_1: i32 = 11;
+pub const BUTTON_RIGHT_TRIGGER_2: i32 = 12;
+pub const BUTTON_MIDDLE_LEFT: i32 = 13;
+pub const BUTTON_MIDDLE: i32 = 14;
+pub const BUTTON_MIDDLE_RIGHT: i32 = 15;
+pub const BUTTON_LEFT_THUMB: i32 = 16;
+pub const BUTTON_RIGHT_THUMB: i32 = 17;
+
+// Axes, values are from -1 to 1
+pub const AXIS_LEFT_X: i32 = 0;
+pub const AXIS_LEFT_Y: i32 = 1;
+pub const AXIS_RIGHT_X: i32 = 2;
+pub const AXIS_RIGHT_Y: i32 = 3;
+pub const AXIS_LEFT_TRIGGER: i32 = 4;
+pub const AXIS_RIGHT_TRIGGER: i32 = 5;
+
+/// Return the button named `name`, the name of its constant with or without
+/// the `BUTTON_` prefix. Case is ignored.
+pub fn button_from_name(name: &str) -> Option<i32> {
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("BUTTON_").unwrap_or(&name);
+    BUTTONS.iter().find(|(n, _)| *n == name).map(|(_, b)| *b)
+}
+
+/// Return the axis named `name`, the name of its constant with or without
+/// the `AXIS_` prefix. Case is ignored.
+pub fn axis_from_name(name: &str) -> Option<i32> {
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("AXIS_").unwrap_or(&name);
+    AXES.iter().find(|(n, _)| *n == name).map(|(_, a)| *a)
+}
+
+const BUTTONS: [(&str, i32); 17] = [
+    ("LEFT_FACE_UP", BUTTON_LEFT_FACE_UP),
+    ("LEFT_FACE_RIGHT", BUTTON_LEFT_FACE_RIGHT),
+    ("LEFT_FACE_DOWN", BUTTON_LEFT_FACE_DOWN),
+    ("LEFT_FACE_LEFT", BUTTON_LEFT_FACE_LEFT),
+    ("RIGHT_FACE_UP", BUTTON_RIGHT_FACE_UP),
+    ("RIGHT_FACE_RIGHT", BUTTON_RIGHT_FACE_RIGHT),
+    ("RIGHT_FACE_DOWN", BUTTON_RIGHT_FACE_DOWN),
+    ("RIGHT_FACE_LEFT", BUTTON_RIGHT_FACE_LEFT),
+    ("LEFT_TRIGGER_1", BUTTON_LEFT_TRIGGER_1),
+    ("LEFT_TRIGGER_2", BUTTON_LEFT_TRIGGER_2),
+    ("RIGHT_TRIGGER_1", BUTTON_RIGHT_TRIGGER_1),
+    ("RIGHT_TRIGGER_2", BUTTON_RIGHT_TRIGGER_2),
+    ("MIDDLE_LEFT", BUTTON_MIDDLE_LEFT),
+    ("MIDDLE", BUTTON_MIDDLE),
+    ("MIDDLE_RIGHT", BUTTON_MIDDLE_RIGHT),
+    ("LEFT_THUMB", BUTTON_LEFT_THUMB),
+    ("RIGHT_THUMB", BUTTON_RIGHT_THUMB),
+];
+
+const AXES: [(&str, i32); 6] = [
+    ("LEFT_X", AXIS_LEFT_X),
+    ("LEFT_Y", AXIS_LEFT_Y),
+    ("RIGHT_X", AXIS_RIGHT_X),
+    ("RIGHT_Y", AXIS_RIGHT_Y),
+    ("LEFT_TRIGGER", AXIS_LEFT_TRIGGER),
+    ("RIGHT_TRIGGER", AXIS_RIGHT_TRIGGER),
+];
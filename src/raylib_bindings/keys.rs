@@ -1,3 +1,26 @@
+//! Keyboard keys, the same values as `KeyboardKey` in `raylib.h`
+
+/// No key pressed
+pub const KEY_NULL: i32 = 0;
+
+// Alphanumeric keys
+pub const KEY_APOSTROPHE: i32 = 39;
+pub const KEY_COMMA: i32 = 44;
+pub const KEY_MINUS: i32 = 45;
+pub const KEY_PERIOD: i32 = 46;
+pub const KEY_SLASH: i32 = 47;
+pub const KEY_ZERO: i32 = 48;
+pub const KEY_ONE: i32 = 49;
+pub const KEY_TWO: i32 = 50;
+pub const KEY_THREE: i32 = 51;
+pub const KEY_FOUR: i32 = 52;
+pub const KEY_FIVE: i32 = 53;
+pub const KEY_SIX: i32 = 54;
+pub const KEY_SEVEN: i32 = 55;
+pub const KEY_EIGHT: i32 = 56;
+pub const KEY_NINE: i32 = 57;
+pub const KEY_SEMICOLON: i32 = 59;
+pub const KEY_EQUAL: i32 = 61;
 pub const KEY_A: i32 = 65;
 pub const KEY_B: i32 = 66;
 pub const KEY_C: i32 = 67;
@@ -24,8 +47,55 @@ pub const KEY_W: i32 = 87;
 pub const KEY_X: i32 = 88;
 pub const KEY_Y: i32 = 89;
 pub const KEY_Z: i32 = 90;
+pub const KEY_LEFT_BRACKET: i32 = 91;
+pub const KEY_BACKSLASH: i32 = 92;
+pub const KEY_RIGHT_BRACKET: i32 = 93;
+pub const KEY_GRAVE: i32 = 96;
+
+// Function keys
+pub const KEY_SPACE: i32 = 32;
+pub const KEY_ESCAPE: i32 = 256;
+pub const KEY_ENTER: i32 = 257;
+pub const KEY_TAB: i32 = 258;
+pub const KEY_BACKSPACE: i32 = 259;
+pub const KEY_INSERT: i32 = 260;
+pub const KEY_DELETE: i32 = 261;
 pub const KEY_RIGHT: i32 = 262;
 pub const KEY_LEFT: i32 = 263;
+pub const KEY_DOWN: i32 = 264;
+pub const KEY_UP: i32 = 265;
+pub const KEY_PAGE_UP: i32 = 266;
+pub const KEY_PAGE_DOWN: i32 = 267;
+pub const KEY_HOME: i32 = 268;
+pub const KEY_END: i32 = 269;
+pub const KEY_CAPS_LOCK: i32 = 280;
+pub const KEY_SCROLL_LOCK: i32 = 281;
+pub const KEY_NUM_LOCK: i32 = 282;
+pub const KEY_PRINT_SCREEN: i32 = 283;
+pub const KEY_PAUSE: i32 = 284;
+pub const KEY_F1: i32 = 290;
+pub const KEY_F2: i32 = 291;
+pub const KEY_F3: i32 = 292;
+pub const KEY_F4: i32 = 293;
+pub const KEY_F5: i32 = 294;
+pub const KEY_F6: i32 = 295;
+pub const KEY_F7: i32 = 296;
+pub const KEY_F8: i32 = 297;
+pub const KEY_F9: i32 = 298;
+pub const KEY_F10: i32 = 299;
+pub const KEY_F11: i32 = 300;
+pub const KEY_F12: i32 = 301;
+pub const KEY_LEFT_SHIFT: i32 = 340;
+pub const KEY_LEFT_CONTROL: i32 = 341;
+pub const KEY_LEFT_ALT: i32 = 342;
+pub const KEY_LEFT_SUPER: i32 = 343;
+pub const KEY_RIGHT_SHIFT: i32 = 344;
+pub const KEY_RIGHT_CONTROL: i32 = 345;
+pub const KEY_RIGHT_ALT: i32 = 346;
+pub const KEY_RIGHT_SUPER: i32 = 347;
+pub const KEY_KB_MENU: i32 = 348;
+
+// Keypad keys
 pub const KEY_KP_0: i32 = 320;
 pub const KEY_KP_1: i32 = 321;
 pub const KEY_KP_2: i32 = 322;
@@ -36,3 +106,144 @@ pub const KEY_KP_6: i32 = 326;
 pub const KEY_KP_7: i32 = 327;
 pub const KEY_KP_8: i32 = 328;
 pub const KEY_KP_9: i32 = 329;
+pub const KEY_KP_DECIMAL: i32 = 330;
+pub const KEY_KP_DIVIDE: i32 = 331;
+pub const KEY_KP_MULTIPLY: i32 = 332;
+pub const KEY_KP_SUBTRACT: i32 = 333;
+pub const KEY_KP_ADD: i32 = 334;
+pub const KEY_KP_ENTER: i32 = 335;
+pub const KEY_KP_EQUAL: i32 = 336;
+
+// Android keys
+pub const KEY_BACK: i32 = 4;
+pub const KEY_MENU: i32 = 5;
+pub const KEY_VOLUME_UP: i32 = 24;
+pub const KEY_VOLUME_DOWN: i32 = 25;
+
+/// Return the key named `name`, the name of its constant with or without
+/// the `KEY_` prefix. Case is ignored and digits can be written as is.
+pub fn from_name(name: &str) -> Option<i32> {
+    if let [d @ b'0'..=b'9'] = name.as_bytes() {
+        return Some(KEY_ZERO + (d - b'0') as i32);
+    }
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("KEY_").unwrap_or(&name);
+    NAMES.iter().find(|(n, _)| *n == name).map(|(_, k)| *k)
+}
+
+/// Return the name of `key`, without the `KEY_` prefix
+pub fn name(key: i32) -> Option<&'static str> {
+    NAMES.iter().find(|(_, k)| *k == key).map(|(n, _)| *n)
+}
+
+const NAMES: [(&str, i32); 109] = [
+    ("APOSTROPHE", KEY_APOSTROPHE),
+    ("COMMA", KEY_COMMA),
+    ("MINUS", KEY_MINUS),
+    ("PERIOD", KEY_PERIOD),
+    ("SLASH", KEY_SLASH),
+    ("ZERO", KEY_ZERO),
+    ("ONE", KEY_ONE),
+    ("TWO", KEY_TWO),
+    ("THREE", KEY_THREE),
+    ("FOUR", KEY_FOUR),
+    ("FIVE", KEY_FIVE),
+    ("SIX", KEY_SIX),
+    ("SEVEN", KEY_SEVEN),
+    ("EIGHT", KEY_EIGHT),
+    ("NINE", KEY_NINE),
+    ("SEMICOLON", KEY_SEMICOLON),
+    ("EQUAL", KEY_EQUAL),
+    ("A", KEY_A),
+    ("B", KEY_B),
+    ("C", KEY_C),
+    ("D", KEY_D),
+    ("E", KEY_E),
+    ("F", KEY_F),
+    ("G", KEY_G),
+    ("H", KEY_H),
+    ("I", KEY_I),
+    ("J", KEY_J),
+    ("K", KEY_K),
+    ("L", KEY_L),
+    ("M", KEY_M),
+    ("N", KEY_N),
+    ("O", KEY_O),
+    ("P", KEY_P),
+    ("Q", KEY_Q),
+    ("R", KEY_R),
+    ("S", KEY_S),
+    ("T", KEY_T),
+    ("U", KEY_U),
+    ("V", KEY_V),
+    ("W", KEY_W),
+    ("X", KEY_X),
+    ("Y", KEY_Y),
+    ("Z", KEY_Z),
+    ("LEFT_BRACKET", KEY_LEFT_BRACKET),
+    ("BACKSLASH", KEY_BACKSLASH),
+    ("RIGHT_BRACKET", KEY_RIGHT_BRACKET),
+    ("GRAVE", KEY_GRAVE),
+    ("SPACE", KEY_SPACE),
+    ("ESCAPE", KEY_ESCAPE),
+    ("ENTER", KEY_ENTER),
+    ("TAB", KEY_TAB),
+    ("BACKSPACE", KEY_BACKSPACE),
+    ("INSERT", KEY_INSERT),
+    ("DELETE", KEY_DELETE),
+    ("RIGHT", KEY_RIGHT),
+    ("LEFT", KEY_LEFT),
+    ("DOWN", KEY_DOWN),
+    ("UP", KEY_UP),
+    ("PAGE_UP", KEY_PAGE_UP),
+    ("PAGE_DOWN", KEY_PAGE_DOWN),
+    ("HOME", KEY_HOME),
+    ("END", KEY_END),
+    ("CAPS_LOCK", KEY_CAPS_LOCK),
+    ("SCROLL_LOCK", KEY_SCROLL_LOCK),
+    ("NUM_LOCK", KEY_NUM_LOCK),
+    ("PRINT_SCREEN", KEY_PRINT_SCREEN),
+    ("PAUSE", KEY_PAUSE),
+    ("F1", KEY_F1),
+    ("F2", KEY_F2),
+    ("F3", KEY_F3),
+    ("F4", KEY_F4),
+    ("F5", KEY_F5),
+    ("F6", KEY_F6),
+    ("F7", KEY_F7),
+    ("F8", KEY_F8),
+    ("F9", KEY_F9),
+    ("F10", KEY_F10),
+    ("F11", KEY_F11),
+    ("F12", KEY_F12),
+    ("LEFT_SHIFT", KEY_LEFT_SHIFT),
+    ("LEFT_CONTROL", KEY_LEFT_CONTROL),
+    ("LEFT_ALT", KEY_LEFT_ALT),
+    ("LEFT_SUPER", KEY_LEFT_SUPER),
+    ("RIGHT_SHIFT", KEY_RIGHT_SHIFT),
+    ("RIGHT_CONTROL", KEY_RIGHT_CONTROL),
+    ("RIGHT_ALT", KEY_RIGHT_ALT),
+    ("RIGHT_SUPER", KEY_RIGHT_SUPER),
+    ("KB_MENU", KEY_KB_MENU),
+    ("KP_0", KEY_KP_0),
+    ("KP_1", KEY_KP_1),
+    ("KP_2", KEY_KP_2),
+    ("KP_3", KEY_KP_3),
+    ("KP_4", KEY_KP_4),
+    ("KP_5", KEY_KP_5),
+    ("KP_6", KEY_KP_6),
+    ("KP_7", KEY_KP_7),
+    ("KP_8", KEY_KP_8),
+    ("KP_9", KEY_KP_9),
+    ("KP_DECIMAL", KEY_KP_DECIMAL),
+    ("KP_DIVIDE", KEY_KP_DIVIDE),
+    ("KP_MULTIPLY", KEY_KP_MULTIPLY),
+    ("KP_SUBTRACT", KEY_KP_SUBTRACT),
+    ("KP_ADD", KEY_KP_ADD),
+    ("KP_ENTER", KEY_KP_ENTER),
+    ("KP_EQUAL", KEY_KP_EQUAL),
+    ("BACK", KEY_BACK),
+    ("MENU", KEY_MENU),
+    ("VOLUME_UP", KEY_VOLUME_UP),
+    ("VOLUME_DOWN", KEY_VOLUME_DOWN),
+];
@@ -0,0 +1,45 @@
+use std::ffi::c_void;
+
+/// Pixel format of 8 bits per channel RGBA, `PIXELFORMAT_UNCOMPRESSED_R8G8B8A8`
+pub const PIXELFORMAT_UNCOMPRESSED_R8G8B8A8: i32 = 7;
+/// Nearest neighbour filtering, `TEXTURE_FILTER_POINT`
+pub const TEXTURE_FILTER_POINT: i32 = 0;
+
+/// An image in CPU memory, raylib doesn't keep `data` when it is loaded in
+/// a texture
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Image {
+    pub(super) data: *mut c_void,
+    pub width: i32,
+    pub height: i32,
+    mipmaps: i32,
+    format: i32,
+}
+
+#[cfg(feature = "raylib")]
+impl Image {
+    /// Describe RGBA `pixels` of `width` x `height`, the image must not
+    /// outlive them
+    pub(super) fn rgba(pixels: &[u8], width: i32, height: i32) -> Image {
+        assert_eq!(pixels.len(), width as usize * height as usize * 4);
+        Image {
+            data: pixels.as_ptr() as *mut c_void,
+            width,
+            height,
+            mipmaps: 1,
+            format: PIXELFORMAT_UNCOMPRESSED_R8G8B8A8,
+        }
+    }
+}
+
+/// A texture in GPU memory
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Texture2D {
+    id: u32,
+    pub width: i32,
+    pub height: i32,
+    mipmaps: i32,
+    format: i32,
+}
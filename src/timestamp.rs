@@ -0,0 +1,63 @@
+//! ## Timestamped file names
+//! Screenshots and videos taken from the frontend are named after the time
+//! they are taken, so they never overwrite each other. The ROM browser also
+//! shows when ROMs were last played.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Return a path in `dir` made of `prefix`, the current UTC date and time,
+/// and `ext`. For example `chip8-20240501-134502.gif`. A number is added if
+/// the file already exists. Directories are named without extension, when
+/// `ext` is empty.
+pub fn file_name(dir: &Path, prefix: &str, ext: &str) -> PathBuf {
+    let (year, month, day, hour, minute, second) = civil(now());
+    let stem = format!("{prefix}-{year:04}{month:02}{day:02}-{hour:02}{minute:02}{second:02}");
+
+    let ext = if ext.is_empty() {
+        String::new()
+    } else {
+        format!(".{ext}")
+    };
+    let mut path = dir.join(format!("{stem}{ext}"));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{stem}-{n}{ext}"));
+        n += 1;
+    }
+    path
+}
+
+/// Return the number of seconds since the epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Return the UTC date and time `secs` after the epoch, like
+/// `2024-05-01 13:45`
+pub fn date_time(secs: u64) -> String {
+    let (year, month, day, hour, minute, _) = civil(secs);
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}")
+}
+
+/// Return the year, month, day, hour, minute and second of the UTC time
+/// `secs` after the epoch
+fn civil(secs: u64) -> (i64, i64, i64, u64, u64, u64) {
+    let (days, time) = (secs / 86400, secs % 86400);
+
+    // Convert days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    (year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
@@ -0,0 +1,267 @@
+//! ## Execution trace
+//! A [`Tracer`] writes one record per executed instruction. The record is
+//! taken before the instruction is executed and contains:
+//! - `cycle`: number of instructions executed so far
+//! - `pc`, `opcode` and `mnemonic` of the instruction
+//! - `v0` to `vf`, `i`, `dt` and `st`
+//!
+//! Records are written as JSON lines or as CSV with a header. Addresses and
+//! opcodes are written in hexadecimal, other values in decimal.
+//!
+//! [`first_divergence`] compares two traces. As a reference trace can come
+//! from another emulator, values can be in hexadecimal (`0x` prefix) or in
+//! decimal, V registers can be a `v` array or `v0`..`vf` fields, and only
+//! fields found in both records are compared.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::disasm;
+use crate::emulator::Chip8;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TraceFormat {
+    Jsonl,
+    Csv,
+}
+
+impl TraceFormat {
+    /// Guess the format from the extension of the file, JSON lines is the
+    /// default.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => TraceFormat::Csv,
+            _ => TraceFormat::Jsonl,
+        }
+    }
+}
+
+pub struct Tracer {
+    out: Box<dyn Write>,
+    format: TraceFormat,
+    cycle: u64,
+}
+
+impl Tracer {
+    pub fn new(out: Box<dyn Write>, format: TraceFormat) -> Self {
+        Tracer {
+            out,
+            format,
+            cycle: 0,
+        }
+    }
+
+    /// Create a tracer that writes in `path`. The format is guessed from the
+    /// extension.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = fs::File::create(path)?;
+        Ok(Self::new(
+            Box::new(io::BufWriter::new(file)),
+            TraceFormat::from_path(path),
+        ))
+    }
+
+    /// Write the record of the instruction that is about to be executed
+    pub fn record(&mut self, chip: &Chip8) -> io::Result<()> {
+        let pc = chip.pc();
+        let opcode = chip.opcode_at(pc);
+        let mnemonic = disasm::mnemonic(opcode);
+        let v = chip.vregs();
+
+        match self.format {
+            TraceFormat::Jsonl => {
+                let v: Vec<String> = v.iter().map(|v| v.to_string()).collect();
+                writeln!(
+                    self.out,
+                    r#"{{"cycle":{},"pc":"{pc:#06x}","opcode":"{opcode:#06x}","mnemonic":"{mnemonic}","v":[{}],"i":"{:#06x}","dt":{},"st":{}}}"#,
+                    self.cycle,
+                    v.join(","),
+                    chip.i(),
+                    chip.delay_timer(),
+                    chip.sound_timer()
+                )?;
+            }
+            TraceFormat::Csv => {
+                if self.cycle == 0 {
+                    let v: Vec<String> = (0..v.len()).map(|x| format!("v{x:x}")).collect();
+                    writeln!(self.out, "cycle,pc,opcode,mnemonic,{},i,dt,st", v.join(","))?;
+                }
+                let v: Vec<String> = v.iter().map(|v| v.to_string()).collect();
+                writeln!(
+                    self.out,
+                    r#"{},{pc:#06x},{opcode:#06x},"{mnemonic}",{},{:#06x},{},{}"#,
+                    self.cycle,
+                    v.join(","),
+                    chip.i(),
+                    chip.delay_timer(),
+                    chip.sound_timer()
+                )?;
+            }
+        }
+
+        self.cycle += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// A record of a trace: the name of a field and its value
+pub type Record = BTreeMap<String, u64>;
+
+/// The first record where two traces diverge
+pub struct Divergence {
+    /// Index of the record in the traces
+    pub index: usize,
+    pub ours: Option<Record>,
+    pub reference: Option<Record>,
+    /// Fields that are different, empty if one of the trace is shorter
+    pub fields: Vec<String>,
+}
+
+/// Read a trace written as JSON lines or as CSV
+pub fn read_trace(path: &Path) -> io::Result<Vec<Record>> {
+    let content = fs::read_to_string(path)?;
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty()).peekable();
+
+    let invalid = |n: usize, msg: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}:{n}: {msg}", path.display()),
+        )
+    };
+
+    let mut records = vec![];
+
+    if lines
+        .peek()
+        .is_some_and(|l| l.trim_start().starts_with('{'))
+    {
+        for (n, line) in lines.enumerate() {
+            let value: Value =
+                serde_json::from_str(line).map_err(|e| invalid(n + 1, &e.to_string()))?;
+            let Value::Object(fields) = value else {
+                return Err(invalid(n + 1, "expected an object"));
+            };
+
+            let mut record = Record::new();
+            for (name, value) in fields {
+                let name = name.to_lowercase();
+                match value {
+                    Value::Array(regs) if name == "v" => {
+                        for (x, v) in regs.iter().enumerate() {
+                            if let Some(v) = json_number(v) {
+                                record.insert(format!("v{x:x}"), v);
+                            }
+                        }
+                    }
+                    value => {
+                        if let Some(v) = json_number(&value) {
+                            record.insert(name, v);
+                        }
+                    }
+                }
+            }
+            records.push(record);
+        }
+    } else {
+        let Some(header) = lines.next() else {
+            return Ok(records);
+        };
+        let names: Vec<String> = split_csv(header)
+            .iter()
+            .map(|n| n.trim().to_lowercase())
+            .collect();
+
+        for line in lines {
+            let record = names
+                .iter()
+                .zip(split_csv(line))
+                .filter_map(|(name, value)| Some((name.clone(), parse_number(&value)?)))
+                .collect();
+            records.push(record);
+        }
+    }
+
+    Ok(records)
+}
+
+/// Return the first record where `ours` and `reference` diverge. Fields
+/// listed in `ignore` are not compared.
+pub fn first_divergence(
+    ours: &[Record],
+    reference: &[Record],
+    ignore: &[String],
+) -> Option<Divergence> {
+    for index in 0..ours.len().max(reference.len()) {
+        let (a, b) = match (ours.get(index), reference.get(index)) {
+            (Some(a), Some(b)) => (a, b),
+            (a, b) => {
+                return Some(Divergence {
+                    index,
+                    ours: a.cloned(),
+                    reference: b.cloned(),
+                    fields: vec![],
+                })
+            }
+        };
+
+        let fields: Vec<String> = a
+            .iter()
+            .filter(|(name, _)| !ignore.contains(name))
+            .filter(|(name, value)| b.get(*name).is_some_and(|v| v != *value))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        if !fields.is_empty() {
+            return Some(Divergence {
+                index,
+                ours: Some(a.clone()),
+                reference: Some(b.clone()),
+                fields,
+            });
+        }
+    }
+
+    None
+}
+
+fn json_number(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => parse_number(s),
+        _ => None,
+    }
+}
+
+/// Parse a value in hexadecimal (with a 0x prefix) or in decimal
+fn parse_number(s: &str) -> Option<u64> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Split a CSV line, commas inside double quotes are not separators
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
@@ -0,0 +1,293 @@
+//! ## Video recording
+//! A [`VideoRecorder`] captures the screen at 60 frames per second, scales
+//! it by an integer factor and encodes it as:
+//! - an animated GIF, consecutive identical frames are merged into a longer
+//!   one,
+//! - a raw [Y4M](https://wiki.multimedia.cx/index.php/YUV4MPEG2) stream
+//!   (4:4:4), that can be given to any video encoder.
+//!
+//! Like the audio [`Recorder`](crate::audio::Recorder) it is driven by the
+//! emulated time, so a frame is captured each 1/60 second whatever the
+//! speed of the emulation is.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::image;
+
+/// Number of frames per second of the video
+pub const FRAME_RATE: u32 = 60;
+/// Default scale of the video
+pub const DEFAULT_SCALE: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VideoFormat {
+    Gif,
+    Y4m,
+}
+
+impl VideoFormat {
+    /// Guess the format from the extension of the file
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("gif") => Some(VideoFormat::Gif),
+            Some(ext) if ext.eq_ignore_ascii_case("y4m") => Some(VideoFormat::Y4m),
+            _ => None,
+        }
+    }
+}
+
+pub struct VideoRecorder {
+    out: BufWriter<File>,
+    format: VideoFormat,
+    /// Resolution of the emulated screen
+    width: usize,
+    height: usize,
+    scale: usize,
+    fg: [u8; 3],
+    bg: [u8; 3],
+    /// Number of frames captured so far
+    frames: u64,
+    /// Fraction of frame not captured yet
+    pending: f64,
+    /// GIF only: last frame and its duration in frames. It is written when a
+    /// different frame is captured.
+    last: Option<(Vec<u8>, u64)>,
+}
+
+impl VideoRecorder {
+    /// Create `path`, the format is guessed from its extension (`.gif` or
+    /// `.y4m`). `width` and `height` are the resolution of the emulated
+    /// screen, `fg` and `bg` the colours of pixels that are set or not.
+    pub fn create(
+        path: &Path,
+        width: usize,
+        height: usize,
+        scale: usize,
+        fg: [u8; 3],
+        bg: [u8; 3],
+    ) -> io::Result<Self> {
+        let format = VideoFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}: expected a .gif or .y4m file", path.display()),
+            )
+        })?;
+        let scale = scale.max(1);
+
+        let mut out = BufWriter::new(File::create(path)?);
+        let (w, h) = (width * scale, height * scale);
+        match format {
+            VideoFormat::Gif => {
+                out.write_all(b"GIF89a")?;
+                out.write_all(&(w as u16).to_le_bytes())?;
+                out.write_all(&(h as u16).to_le_bytes())?;
+                // Global colour table of 2 entries, background is entry 0
+                out.write_all(&[0x80, 0, 0])?;
+                out.write_all(&bg)?;
+                out.write_all(&fg)?;
+                // Loop forever
+                out.write_all(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+            }
+            VideoFormat::Y4m => {
+                writeln!(out, "YUV4MPEG2 W{w} H{h} F{FRAME_RATE}:1 Ip A1:1 C444")?;
+            }
+        }
+
+        Ok(VideoRecorder {
+            out,
+            format,
+            width,
+            height,
+            scale,
+            fg,
+            bg,
+            frames: 0,
+            pending: 0.0,
+            last: None,
+        })
+    }
+
+    /// Number of frames captured so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Return the resolution of the emulated screen
+    pub fn resolution(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Let `seconds` pass, the framebuffer `fb` is captured for each frame
+    /// that starts during this time.
+    pub fn advance(&mut self, seconds: f64, fb: &[u8]) -> io::Result<()> {
+        self.pending += seconds * FRAME_RATE as f64;
+        while self.pending >= 1.0 {
+            self.pending -= 1.0;
+            self.capture(fb)?;
+        }
+        Ok(())
+    }
+
+    /// Capture one frame
+    pub fn capture(&mut self, fb: &[u8]) -> io::Result<()> {
+        match self.format {
+            VideoFormat::Gif => {
+                let pixels = self.indices(fb);
+                match &mut self.last {
+                    Some((last, duration)) if *last == pixels => *duration += 1,
+                    _ => {
+                        if let Some((last, duration)) = self.last.take() {
+                            self.write_gif_frame(&last, duration)?;
+                        }
+                        self.last = Some((pixels, 1));
+                    }
+                }
+            }
+            VideoFormat::Y4m => {
+                let rgb = image::to_rgb(fb, self.width, self.height, self.scale, self.fg, self.bg);
+                let planes: [Vec<u8>; 3] = [
+                    rgb.chunks(3).map(|p| yuv(p).0).collect(),
+                    rgb.chunks(3).map(|p| yuv(p).1).collect(),
+                    rgb.chunks(3).map(|p| yuv(p).2).collect(),
+                ];
+                self.out.write_all(b"FRAME\n")?;
+                for plane in planes {
+                    self.out.write_all(&plane)?;
+                }
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Write the end of the file
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.format == VideoFormat::Gif {
+            if let Some((last, duration)) = self.last.take() {
+                self.write_gif_frame(&last, duration)?;
+            }
+            self.out.write_all(b"\x3B")?;
+        }
+        self.out.flush()
+    }
+
+    /// Return the index in the palette of each pixel of the scaled screen
+    fn indices(&self, fb: &[u8]) -> Vec<u8> {
+        let (w, h) = (self.width * self.scale, self.height * self.scale);
+        let mut pixels = Vec::with_capacity(w * h);
+        for y in 0..h {
+            for x in 0..w {
+                let set = image::is_set(fb, self.width, x / self.scale, y / self.scale);
+                pixels.push(set as u8);
+            }
+        }
+        pixels
+    }
+
+    /// Write a frame that lasts `duration` frames of the video
+    fn write_gif_frame(&mut self, pixels: &[u8], duration: u64) -> io::Result<()> {
+        // Delays are in hundredths of second, they are rounded from the start
+        // of the video so the error doesn't accumulate.
+        let end = self.frames;
+        let start = end - duration;
+        let centis = |frame: u64| frame * 100 / FRAME_RATE as u64;
+        let delay = (centis(end) - centis(start)).min(u16::MAX as u64) as u16;
+
+        // Graphic control extension
+        self.out.write_all(&[0x21, 0xF9, 0x04, 0x00])?;
+        self.out.write_all(&delay.to_le_bytes())?;
+        self.out.write_all(&[0x00, 0x00])?;
+
+        // Image descriptor of the whole screen
+        let (w, h) = (self.width * self.scale, self.height * self.scale);
+        self.out.write_all(&[0x2C, 0, 0, 0, 0])?;
+        self.out.write_all(&(w as u16).to_le_bytes())?;
+        self.out.write_all(&(h as u16).to_le_bytes())?;
+        self.out.write_all(&[0x00])?;
+
+        // Image data in sub-blocks of at most 255 bytes
+        self.out.write_all(&[GIF_MIN_CODE_SIZE])?;
+        for block in lzw(pixels).chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+        self.out.write_all(&[0x00])
+    }
+}
+
+/// Convert a RGB pixel to YCbCr (BT.601, studio range)
+fn yuv(p: &[u8]) -> (u8, u8, u8) {
+    let (r, g, b) = (p[0] as f32, p[1] as f32, p[2] as f32);
+    let y = 16.0 + 0.257 * r + 0.504 * g + 0.098 * b;
+    let u = 128.0 - 0.148 * r - 0.291 * g + 0.439 * b;
+    let v = 128.0 + 0.439 * r - 0.368 * g - 0.071 * b;
+    (y.round() as u8, u.round() as u8, v.round() as u8)
+}
+
+/// GIF doesn't allow a code size lower than 2, even for 2 colours
+const GIF_MIN_CODE_SIZE: u8 = 2;
+/// Codes are at most 12 bits
+const GIF_MAX_CODES: u16 = 4096;
+
+/// Compress palette indices with the variable width LZW of GIF
+fn lzw(pixels: &[u8]) -> Vec<u8> {
+    let clear = 1_u16 << GIF_MIN_CODE_SIZE;
+    let end = clear + 1;
+
+    let mut out = vec![];
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let mut emit = |code: u16, width: u32, out: &mut Vec<u8>| {
+        acc |= (code as u32) << bits;
+        bits += width;
+        while bits >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            bits -= 8;
+        }
+    };
+
+    let mut dict: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut width = GIF_MIN_CODE_SIZE as u32 + 1;
+    emit(clear, width, &mut out);
+
+    let Some((first, rest)) = pixels.split_first() else {
+        emit(end, width, &mut out);
+        emit(0, 7, &mut out); // flush the last bits
+        return out;
+    };
+
+    let mut prefix = *first as u16;
+    for &k in rest {
+        if let Some(&code) = dict.get(&(prefix, k)) {
+            prefix = code;
+            continue;
+        }
+
+        emit(prefix, width, &mut out);
+        if next < GIF_MAX_CODES {
+            dict.insert((prefix, k), next);
+            next += 1;
+            // The decoder adds its entries one code later, so the width
+            // grows when the code after the largest one is added.
+            if next > (1 << width) && width < 12 {
+                width += 1;
+            }
+        } else {
+            emit(clear, width, &mut out);
+            dict.clear();
+            next = end + 1;
+            width = GIF_MIN_CODE_SIZE as u32 + 1;
+        }
+        prefix = k as u16;
+    }
+
+    emit(prefix, width, &mut out);
+    emit(end, width, &mut out);
+    emit(0, 7, &mut out); // flush the last bits
+    out
+}
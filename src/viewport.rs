@@ -0,0 +1,54 @@
+//! ## Placement of the display in the window
+//! The display keeps square pixels, so it is letterboxed: it is as large as
+//! possible and centered, with borders on the sides or at the top and the
+//! bottom. Its pixels can be rounded to an integer size to keep them all the
+//! same.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Viewport {
+    /// Position of the top left corner of the display in the window
+    pub x: f32,
+    pub y: f32,
+    /// Size of a pixel of the display
+    pub pixel_size: f32,
+    width: usize,
+    height: usize,
+}
+
+impl Viewport {
+    /// Place a display of `resolution` pixels in a window of `window` size
+    pub fn letterbox(window: (i32, i32), resolution: (usize, usize), integer_scale: bool) -> Self {
+        let (width, height) = resolution;
+        let scale_x = window.0.max(0) as f32 / width as f32;
+        let scale_y = window.1.max(0) as f32 / height as f32;
+        let mut pixel_size = scale_x.min(scale_y);
+        if integer_scale {
+            // Pixels are at least 1 even if the window is too small
+            pixel_size = pixel_size.floor().max(1.0);
+        }
+
+        Viewport {
+            x: ((window.0 as f32 - width as f32 * pixel_size) / 2.0).floor(),
+            y: ((window.1 as f32 - height as f32 * pixel_size) / 2.0).floor(),
+            pixel_size,
+            width,
+            height,
+        }
+    }
+
+    /// Return the size of the display in the window
+    pub fn size(&self) -> (f32, f32) {
+        (
+            self.width as f32 * self.pixel_size,
+            self.height as f32 * self.pixel_size,
+        )
+    }
+
+    /// Return the position in the window of the pixel (x, y) of the display
+    pub fn pixel(&self, x: usize, y: usize) -> (f32, f32) {
+        (
+            self.x + x as f32 * self.pixel_size,
+            self.y + y as f32 * self.pixel_size,
+        )
+    }
+}
@@ -0,0 +1,74 @@
+//! ## ROM hot reload
+//! With `--watch`, the frontend polls the ROM file and reloads it when it
+//! changes, for an edit-assemble-run loop. A change is detected by the
+//! modification time of the file, then confirmed by its SHA-1 so saving
+//! the same content again doesn't reset the machine.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::sha1;
+
+/// Time between two checks of the file
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct RomWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    hash: [u8; 20],
+    last_poll: Instant,
+}
+
+impl RomWatcher {
+    /// Watch the ROM at `path`, whose current content is `rom`
+    pub fn new(path: &Path, rom: &[u8]) -> Self {
+        RomWatcher {
+            path: path.to_path_buf(),
+            modified: fs::metadata(path).and_then(|m| m.modified()).ok(),
+            hash: sha1::digest(rom),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Check the file if the last check is older than [`POLL_INTERVAL`]
+    /// and return its new content if it changed
+    pub fn poll(&mut self) -> Option<Vec<u8>> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Instant::now();
+
+        match self.check() {
+            Ok(rom) => rom,
+            Err(e) => {
+                log::warn!("Cannot check {}: {e}", self.path.display());
+                None
+            }
+        }
+    }
+
+    /// Return the new content of the file if it changed since the last check
+    pub fn check(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let modified = fs::metadata(&self.path)?.modified().ok();
+        if modified == self.modified {
+            return Ok(None);
+        }
+
+        let rom = fs::read(&self.path)?;
+        // An empty file is being written by the assembler, its next
+        // modification gives the ROM
+        if rom.is_empty() {
+            return Ok(None);
+        }
+        self.modified = modified;
+
+        let hash = sha1::digest(&rom);
+        if hash == self.hash {
+            return Ok(None);
+        }
+        self.hash = hash;
+        Ok(Some(rom))
+    }
+}
@@ -0,0 +1,73 @@
+//! Sound rendered in WAV files by a headless run
+
+use chip8_emulator::audio::{Beeper, Recorder, SAMPLE_RATE};
+use chip8_emulator::emulator::Chip8;
+use chip8_emulator::headless::{Headless, DEFAULT_IPF};
+use chip8_emulator::keyscript::KeyScript;
+use std::fs;
+use std::path::Path;
+
+/// Number of samples in a frame at 60 frames per second
+const FRAME_SAMPLES: usize = SAMPLE_RATE as usize / 60;
+
+/// Run `program` for `frames` frames and return the samples of the WAV file
+fn record(name: &str, program: &[u16], frames: u64) -> Vec<i16> {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.wav"));
+
+    let mut runner = Headless::new(
+        Chip8::with_program(program).build(),
+        DEFAULT_IPF,
+        KeyScript::default(),
+    );
+    runner.set_recorder(Some(
+        Recorder::create(&path, &Beeper::new(SAMPLE_RATE)).unwrap(),
+    ));
+    runner.run_frames(frames).unwrap();
+    runner.set_recorder(None);
+
+    let wav = fs::read(&path).unwrap();
+    let u32_at = |i: usize| u32::from_le_bytes(wav[i..i + 4].try_into().unwrap());
+    let u16_at = |i: usize| u16::from_le_bytes(wav[i..i + 2].try_into().unwrap());
+
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(u32_at(4) as usize, wav.len() - 8);
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u16_at(20), 1, "PCM");
+    assert_eq!(u16_at(22), 1, "mono");
+    assert_eq!(u32_at(24), SAMPLE_RATE);
+    assert_eq!(u16_at(34), 16, "bits per sample");
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(u32_at(40) as usize, wav.len() - 44);
+
+    wav[44..]
+        .chunks(2)
+        .map(|s| i16::from_le_bytes([s[0], s[1]]))
+        .collect()
+}
+
+#[test]
+fn silent_program() {
+    // JP 0x200
+    let samples = record("silent", &[0x1200], 10);
+    assert_eq!(samples.len(), 10 * FRAME_SAMPLES);
+    assert!(samples.iter().all(|s| *s == 0));
+}
+
+#[test]
+fn tone_stops_with_sound_timer() {
+    // LD V0, 30; LD ST, V0; JP 0x204
+    let samples = record("tone", &[0x601E, 0xF018, 0x1204], 60);
+    assert_eq!(samples.len(), 60 * FRAME_SAMPLES);
+
+    let frame = |n: usize| &samples[n * FRAME_SAMPLES..(n + 1) * FRAME_SAMPLES];
+    // The timer is decremented at 60 Hz, so the tone lasts half a second,
+    // then it fades out during the next frame
+    assert!(
+        (0..29).all(|n| frame(n).iter().any(|s| *s != 0)),
+        "tone is stopped before the sound timer"
+    );
+    assert!(
+        (30..60).all(|n| frame(n).iter().all(|s| *s == 0)),
+        "tone is still played after the sound timer"
+    );
+}
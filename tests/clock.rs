@@ -0,0 +1,34 @@
+//! Ticks of 1/60 s run for the time spent between two renders
+
+use chip8_emulator::clock::{Clock, TICK};
+
+#[test]
+fn ticks_follow_the_real_time() {
+    let mut clock = Clock::new();
+    // A display at 144 Hz runs a tick every 2 or 3 frames
+    let ticks: u32 = (0..144).map(|_| clock.advance(1.0 / 144.0, 1.0)).sum();
+    assert!((59..=60).contains(&ticks), "{ticks}");
+
+    let mut clock = Clock::new();
+    assert_eq!(clock.advance(TICK / 2.0, 1.0), 0);
+    assert_eq!(clock.advance(TICK / 2.0 + 1e-9, 1.0), 1);
+}
+
+#[test]
+fn speed() {
+    let mut clock = Clock::new();
+    assert_eq!(clock.advance(TICK + 1e-9, 4.0), 4);
+
+    let ticks: u32 = (0..8).map(|_| clock.advance(TICK, 0.25)).sum();
+    assert_eq!(ticks, 2);
+}
+
+#[test]
+fn long_stalls_are_not_caught_up() {
+    let mut clock = Clock::new();
+    assert_eq!(clock.advance(10.0, 1.0), 15);
+
+    clock.advance(TICK * 0.9, 1.0);
+    clock.reset();
+    assert_eq!(clock.advance(TICK * 0.5, 1.0), 0);
+}
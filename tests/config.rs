@@ -0,0 +1,130 @@
+//! Options read from TOML files and from the command line
+
+use chip8_emulator::config::{parse_color, Config, Variant};
+use chip8_emulator::emulator::Quirks;
+use chip8_emulator::palette::{Palette, PRESETS};
+use std::path::Path;
+
+const CONFIG: &str = r##"
+scale = 10
+ipf = 8
+foreground = "#FFB000"
+variant = "schip"
+
+[rom."pong.ch8"]
+ipf = 12
+quirks = "vip"
+"##;
+
+#[test]
+fn top_level_options() {
+    let mut config = Config::default();
+    config
+        .apply_toml(CONFIG, Path::new("roms/tetris.ch8"))
+        .unwrap();
+
+    assert_eq!(config.scale, 10);
+    assert_eq!(config.ipf, 8);
+    assert_eq!(config.palette().foreground(), [0xFF, 0xB0, 0x00]);
+    assert_eq!(config.variant, Variant::Schip);
+    assert_eq!(config.quirks(), Quirks::SCHIP);
+}
+
+#[test]
+fn rom_options_override_top_level() {
+    let mut config = Config::default();
+    config
+        .apply_toml(CONFIG, Path::new("roms/pong.ch8"))
+        .unwrap();
+
+    assert_eq!(config.scale, 10);
+    assert_eq!(config.ipf, 12);
+    assert_eq!(config.quirks(), Quirks::VIP);
+}
+
+#[test]
+fn command_line_overrides_file() {
+    let mut config = Config::default();
+    config.apply_toml(CONFIG, Path::new("pong.ch8")).unwrap();
+    config.set("ipf", "20").unwrap();
+    assert_eq!(config.ipf, 20);
+}
+
+#[test]
+fn invalid_values() {
+    let mut config = Config::default();
+    assert!(config.set("ipf", "0").is_err());
+    assert!(config.set("scale", "big").is_err());
+    assert!(config.set("volume", "101").is_err());
+    assert!(config.set("quirks", "cosmac").is_err());
+    assert!(config.set("speed", "1").is_err());
+    assert!(config.set("persistence", "ghost").is_err());
+    assert!(config.set("fade-frames", "0").is_err());
+    assert!(config.set("seed", "9223372036854775808").is_err());
+
+    let err = config
+        .apply_toml("[rom.\"pong.ch8\"]\nipf = -1\n", Path::new("pong.ch8"))
+        .unwrap_err();
+    assert!(err.contains("rom.\"pong.ch8\".ipf"), "{err}");
+}
+
+#[test]
+fn colors() {
+    assert_eq!(parse_color("#00FF00"), Ok([0, 255, 0]));
+    assert_eq!(parse_color("ffb000"), Ok([255, 176, 0]));
+    assert!(parse_color("#0F0").is_err());
+    assert!(parse_color("#GG0000").is_err());
+}
+
+#[test]
+fn palettes() {
+    for name in PRESETS {
+        assert_eq!(name.parse::<Palette>().unwrap().name(), name);
+    }
+    let classic = Palette::default();
+    assert_eq!(classic.background(), [0, 0, 0]);
+    assert_eq!(classic.foreground(), [255, 255, 255]);
+
+    // Missing colours are the foreground
+    let custom: Palette = "#000000, #FFB000".parse().unwrap();
+    assert_eq!(custom.color(3), [0xFF, 0xB0, 0x00]);
+    assert!("#000000".parse::<Palette>().is_err());
+    assert!("#000000,#111111,#222222,#333333,#444444"
+        .parse::<Palette>()
+        .is_err());
+    assert!("sepia".parse::<Palette>().is_err());
+
+    let mut config = Config::default();
+    config
+        .apply_toml(
+            "palette = [\"#000000\", \"#FF0000\", \"#00FF00\", \"#0000FF\"]\nbackground = \"#101010\"\n",
+            Path::new("pong.ch8"),
+        )
+        .unwrap();
+    let palette = config.palette();
+    assert_eq!(palette.background(), [0x10, 0x10, 0x10]);
+    assert_eq!(palette.foreground(), [0xFF, 0, 0]);
+    assert_eq!(palette.color(3), [0, 0, 0xFF]);
+}
+
+#[test]
+fn toml_round_trip() {
+    let mut config = Config::default();
+    for (name, value) in [
+        ("palette", "#101010,#F0F0F0,#808080"),
+        ("background", "#000020"),
+        ("quirks", "vip"),
+        ("seed", "9223372036854775807"),
+        ("tone", "523.25"),
+        ("fast-forward", "4"),
+        ("scanlines", "true"),
+    ] {
+        config.set(name, value).unwrap();
+    }
+
+    let mut reloaded = Config::default();
+    reloaded
+        .apply_toml(&config.to_toml(), Path::new("pong.ch8"))
+        .unwrap();
+    assert_eq!(reloaded, config);
+}
@@ -0,0 +1,100 @@
+//! Crash bundles written when a ROM fails
+
+use chip8_emulator::config::Config;
+use chip8_emulator::crash::{self, Failure};
+use chip8_emulator::emulator::{Chip8, Chip8Error};
+use chip8_emulator::sha1;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn sha1_digests() {
+    assert_eq!(
+        sha1::hex_digest(b""),
+        "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+    );
+    assert_eq!(
+        sha1::hex_digest(b"abc"),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    // Two blocks once padded
+    assert_eq!(
+        sha1::hex_digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+        "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+    );
+}
+
+#[test]
+fn history_has_the_effects_of_instructions() {
+    // LD V1, 5; LD I, 0x300; then an unknown opcode
+    let mut chip = Chip8::with_program(&[0x6105, 0xA300, 0xFFFF]).build();
+    let failure = crash::catch(|| chip.run(3)).unwrap_err();
+    assert_eq!(failure, Failure::Error(Chip8Error::UnknownOpcode(0xFFFF)));
+
+    let effects: Vec<Vec<String>> = chip.history().iter().map(|e| e.effects()).collect();
+    assert_eq!(effects, [vec!["V1=0x05"], vec!["I=0x0300"], vec![]]);
+
+    chip.set_history_size(1);
+    assert_eq!(chip.history().len(), 1);
+    assert_eq!(chip.history()[0].opcode, 0xFFFF);
+}
+
+#[test]
+fn panics_are_caught() {
+    let failure = crash::catch::<()>(|| panic!("index out of bounds")).unwrap_err();
+    assert_eq!(failure, Failure::Panic("index out of bounds".to_string()));
+    assert_eq!(
+        failure.to_string(),
+        "Emulator panicked: index out of bounds"
+    );
+}
+
+#[test]
+fn bundle_has_what_is_needed_to_reproduce() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("crashes");
+    let rom = [0x61, 0x05, 0xFF, 0xFF];
+    let mut chip = Chip8::default();
+    chip.load_bytes(&rom).unwrap();
+    let failure = crash::catch(|| chip.run(2)).unwrap_err();
+    let config = Config {
+        ipf: 12,
+        ..Config::default()
+    };
+
+    let path = crash::write_bundle(
+        &dir,
+        &chip,
+        &failure,
+        Path::new("roms/bad.ch8"),
+        &rom,
+        &config.to_toml(),
+    )
+    .unwrap();
+    let read = |name: &str| fs::read_to_string(path.join(name)).unwrap();
+
+    assert!(read("error.txt").starts_with("Error: Opcode <0xffff> is unknown\n"));
+    assert_eq!(
+        read("rom.txt"),
+        format!("name: bad.ch8\nsize: 4\nsha1: {}\n", sha1::hex_digest(&rom))
+    );
+    assert_eq!(fs::read(path.join("bad.ch8")).unwrap(), rom);
+
+    let history = read("history.txt");
+    let lines: Vec<&str> = history.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("0x0200  6105") && lines[0].ends_with("V1=0x05"));
+    assert!(lines[1].starts_with("0x0202  ffff"));
+
+    let state: serde_json::Value = serde_json::from_str(&read("state.json")).unwrap();
+    assert_eq!(state["v"][1], 5);
+    assert_eq!(
+        state["memory"].as_str().unwrap().len(),
+        2 * chip.memory().len()
+    );
+
+    let mut reloaded = Config::default();
+    reloaded
+        .apply_toml(&read("config.toml"), Path::new("bad.ch8"))
+        .unwrap();
+    assert_eq!(reloaded, config);
+}
@@ -0,0 +1,322 @@
+//! Sessions of the DAP server, driven by in-memory buffers
+
+use chip8_emulator::dap::DapServer;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// 0x200: V0 = 1, 0x202: V0 += 1, 0x204: jump to 0x202
+const PROGRAM: [u8; 6] = [0x60, 0x01, 0x70, 0x01, 0x12, 0x02];
+const SOURCE_MAP: &str = "# address line\n200 1\n202 2\n204 3\n";
+
+/// Requests given to the server, a chunk at a time
+struct Input {
+    rx: Receiver<Vec<u8>>,
+    chunk: Cursor<Vec<u8>>,
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.chunk.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            match self.rx.recv() {
+                Ok(chunk) => self.chunk = Cursor::new(chunk),
+                // The session is over
+                Err(_) => return Ok(0),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Session {
+    tx: Option<Sender<Vec<u8>>>,
+    output: Output,
+    server: Option<JoinHandle<io::Result<()>>>,
+    seq: u64,
+    /// Messages received and not yet read by a test
+    messages: Vec<Value>,
+}
+
+impl Session {
+    fn start() -> Session {
+        let (tx, rx) = mpsc::channel();
+        let output = Output::default();
+        let out = output.clone();
+        let input = Input {
+            rx,
+            chunk: Cursor::new(vec![]),
+        };
+        Session {
+            tx: Some(tx),
+            output,
+            server: Some(thread::spawn(move || DapServer::new(out).run(input))),
+            seq: 1,
+            messages: vec![],
+        }
+    }
+
+    /// Launch `name`, a copy of the program with its source map
+    fn launch(name: &str, args: Value) -> Session {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("dap");
+        fs::create_dir_all(&dir).unwrap();
+        let path = |ext: &str| -> PathBuf { dir.join(format!("{name}.{ext}")) };
+        fs::write(path("ch8"), PROGRAM).unwrap();
+        fs::write(path("8o"), "v0 := 1\nloop v0 += 1 again\n").unwrap();
+        fs::write(path("map"), SOURCE_MAP).unwrap();
+
+        let mut session = Session::start();
+        session.request("initialize", json!({ "adapterID": "chip8" }));
+        let mut launch = json!({
+            "program": path("ch8"),
+            "source": path("8o"),
+            "sourceMap": path("map"),
+        });
+        launch
+            .as_object_mut()
+            .unwrap()
+            .extend(args.as_object().unwrap().clone());
+        assert_eq!(session.request("launch", launch)["success"], true);
+        session.event("initialized");
+        session
+    }
+
+    fn source(&self, name: &str) -> PathBuf {
+        Path::new(env!("CARGO_TARGET_TMPDIR"))
+            .join("dap")
+            .join(format!("{name}.8o"))
+    }
+
+    /// Send a request and return its response
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        let seq = self.seq;
+        self.seq += 1;
+        let body = json!({
+            "seq": seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        let framed = format!("Content-Length: {}\r\n\r\n{body}", body.len());
+        self.tx.as_ref().unwrap().send(framed.into_bytes()).unwrap();
+        self.wait(|m| m["type"] == "response" && m["request_seq"] == seq)
+    }
+
+    /// Wait for the event `name` and return its body
+    fn event(&mut self, name: &str) -> Value {
+        self.wait(|m| m["type"] == "event" && m["event"] == name)["body"].clone()
+    }
+
+    /// Return the first message received that matches `pred`
+    fn wait(&mut self, pred: impl Fn(&Value) -> bool) -> Value {
+        let start = Instant::now();
+        loop {
+            if let Some(i) = self.messages.iter().position(&pred) {
+                return self.messages.remove(i);
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "no reply");
+            self.receive();
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Parse the messages written by the server
+    fn receive(&mut self) {
+        let mut out = self.output.0.lock().unwrap();
+        while let Some(end) = out.windows(4).position(|w| w == b"\r\n\r\n") {
+            let header = std::str::from_utf8(&out[..end]).unwrap();
+            let length: usize = header
+                .strip_prefix("Content-Length: ")
+                .unwrap()
+                .parse()
+                .unwrap();
+            if out.len() < end + 4 + length {
+                break;
+            }
+            let msg = serde_json::from_slice(&out[end + 4..end + 4 + length]).unwrap();
+            self.messages.push(msg);
+            out.drain(..end + 4 + length);
+        }
+    }
+
+    fn pc(&mut self) -> u64 {
+        let trace = self.request("stackTrace", json!({ "threadId": 1 }));
+        let pc = trace["body"]["stackFrames"][0]["instructionPointerReference"]
+            .as_str()
+            .unwrap();
+        u64::from_str_radix(pc.trim_start_matches("0x"), 16).unwrap()
+    }
+
+    fn register(&mut self, name: &str) -> String {
+        let vars = self.request("variables", json!({ "variablesReference": 1 }));
+        vars["body"]["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|v| v["name"] == name)
+            .unwrap()["value"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // Ending the input ends the session
+        self.tx = None;
+        if let Some(server) = self.server.take() {
+            if !thread::panicking() {
+                server.join().unwrap().unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn continue_stops_on_breakpoints() {
+    let mut session = Session::launch("breakpoints", json!({}));
+    let source = session.source("breakpoints");
+    let bps = session.request(
+        "setBreakpoints",
+        json!({ "source": { "path": source }, "breakpoints": [{ "line": 1 }, { "line": 2 }, { "line": 9 }] }),
+    );
+    let verified: Vec<&Value> = bps["body"]["breakpoints"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bp| &bp["verified"])
+        .collect();
+    assert_eq!(verified, [true, true, false]);
+
+    // The breakpoint on the entry point is hit without stopOnEntry
+    session.request("configurationDone", json!({}));
+    assert_eq!(session.event("stopped")["reason"], "breakpoint");
+    assert_eq!(session.pc(), 0x200);
+
+    session.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(session.event("stopped")["reason"], "breakpoint");
+    assert_eq!(session.pc(), 0x202);
+    assert_eq!(session.register("V0"), "0x01 (1)");
+
+    // Once around the loop
+    session.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(session.event("stopped")["reason"], "breakpoint");
+    assert_eq!(session.pc(), 0x202);
+    assert_eq!(session.register("V0"), "0x02 (2)");
+}
+
+#[test]
+fn instruction_breakpoints() {
+    let mut session = Session::launch("instructions", json!({}));
+    let bps = session.request(
+        "setInstructionBreakpoints",
+        json!({ "breakpoints": [{ "instructionReference": "0x202", "offset": 2 }, { "instructionReference": "0x1000" }] }),
+    );
+    assert_eq!(bps["body"]["breakpoints"][0]["verified"], true);
+    assert_eq!(bps["body"]["breakpoints"][1]["verified"], false);
+
+    session.request("configurationDone", json!({}));
+    assert_eq!(session.event("stopped")["reason"], "breakpoint");
+    assert_eq!(session.pc(), 0x204);
+}
+
+#[test]
+fn stepping() {
+    let mut session = Session::launch("stepping", json!({ "stopOnEntry": true }));
+    session.request("configurationDone", json!({}));
+    assert_eq!(session.event("stopped")["reason"], "entry");
+    assert_eq!(session.pc(), 0x200);
+
+    session.request("stepIn", json!({ "threadId": 1 }));
+    assert_eq!(session.event("stopped")["reason"], "step");
+    assert_eq!(session.pc(), 0x202);
+
+    session.request("next", json!({ "threadId": 1 }));
+    assert_eq!(session.event("stopped")["reason"], "step");
+    assert_eq!(session.pc(), 0x204);
+    assert_eq!(session.register("V0"), "0x02 (2)");
+
+    let frames = session.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(frames["body"]["stackFrames"][0]["line"], 3);
+}
+
+#[test]
+fn read_memory() {
+    let mut session = Session::launch("memory", json!({ "stopOnEntry": true }));
+    session.request("configurationDone", json!({}));
+    session.event("stopped");
+
+    let read = session.request(
+        "readMemory",
+        json!({ "memoryReference": "0x200", "count": 4 }),
+    );
+    assert_eq!(read["body"]["data"], "YAFwAQ==");
+    assert_eq!(read["body"]["unreadableBytes"], 0);
+
+    // Bytes past the end of the memory can't be read
+    let read = session.request(
+        "readMemory",
+        json!({ "memoryReference": "0xffe", "offset": 1, "count": u64::MAX }),
+    );
+    assert_eq!(read["body"]["address"], "0x0fff");
+    // One byte in base64
+    assert_eq!(read["body"]["data"].as_str().unwrap().len(), 4);
+    assert_eq!(read["body"]["unreadableBytes"], u64::MAX - 1);
+
+    let read = session.request(
+        "readMemory",
+        json!({ "memoryReference": "0xffffffffffffffff", "offset": 1, "count": 1 }),
+    );
+    assert_eq!(read["success"], false);
+    let read = session.request(
+        "readMemory",
+        json!({ "memoryReference": "0x200", "offset": -0x201, "count": 1 }),
+    );
+    assert_eq!(read["success"], false);
+}
+
+#[test]
+fn launch_reads_the_database() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("dap-database.json");
+    fs::write(&path, "{ \"pong\": {} }").unwrap();
+    let mut session = Session::start();
+    let launch = session.request(
+        "launch",
+        json!({ "program": "chip8-roms/pong.ch8", "database": path }),
+    );
+    assert_eq!(launch["success"], false);
+
+    let launch = session.request("launch", json!({ "program": "chip8-roms/pong.ch8" }));
+    assert_eq!(launch["success"], true);
+}
+
+#[test]
+fn oversized_messages_end_the_session() {
+    let output = Output::default();
+    let input = Cursor::new(b"Content-Length: 100000000\r\n\r\n".to_vec());
+    DapServer::new(output.clone()).run(input).unwrap();
+    assert!(output.0.lock().unwrap().is_empty());
+}
@@ -0,0 +1,80 @@
+//! Settings of known ROMs found by their SHA-1
+
+use chip8_emulator::config::{Config, Variant};
+use chip8_emulator::database::{Database, DATABASE_FILE};
+use chip8_emulator::emulator::Quirks;
+use chip8_emulator::palette::Palette;
+use std::fs;
+use std::path::Path;
+
+/// SHA-1 of the ROM `12 00`, a jump to itself
+const LOOP_SHA1: &str = "92a5652d382a18e89c4881ec57041fc7d885ca80";
+
+#[test]
+fn bundled_roms_are_known() {
+    let database = Database::load(Path::new(DATABASE_FILE)).unwrap();
+    let pong = fs::read("chip8-roms/pong.ch8").unwrap();
+    let info = database.get(&pong).unwrap();
+    assert_eq!(info.title, "Pong");
+    assert_eq!(info.quirks, Some(Quirks::VIP));
+    assert_eq!(
+        info.key_hints().split(", ").next(),
+        Some("1 left paddle up")
+    );
+
+    for rom in ["1-chip8-logo.ch8", "3-corax+.ch8"] {
+        let bytes = fs::read(Path::new("chip8-roms").join(rom)).unwrap();
+        assert!(
+            database.get(&bytes).is_some(),
+            "{rom} is not in the database"
+        );
+    }
+}
+
+#[test]
+fn settings_are_applied() {
+    let content = r#"{
+        "HASH": {
+            "title": "Loop",
+            "platform": "schip",
+            "ipf": 30,
+            "palette": "amber",
+            "keys": { "A": "fire", "5": "jump" }
+        }
+    }"#
+    .replace("HASH", &LOOP_SHA1.to_uppercase());
+    let database = Database::parse(&content).unwrap();
+    let info = database.get(&[0x12, 0x00]).unwrap();
+    assert_eq!(info.author, None);
+    assert_eq!(info.key_hints(), "5 jump, A fire");
+
+    let mut config = Config::default();
+    info.apply(&mut config);
+    assert_eq!(config.variant, Variant::Schip);
+    assert_eq!(config.quirks(), Quirks::SCHIP);
+    assert_eq!(config.ipf, 30);
+    assert_eq!(config.palette, Palette::preset("amber").unwrap());
+
+    assert!(database.get(&[0x12, 0x02]).is_none());
+}
+
+#[test]
+fn invalid_entries() {
+    let entry = |fields: &str| format!(r#"{{ "{LOOP_SHA1}": {{ {fields} }} }}"#);
+    assert!(Database::parse(&entry(r#""title": "Loop""#)).is_ok());
+    assert!(Database::parse(&entry(r#""author": "Me""#)).is_err());
+    assert!(Database::parse(&entry(r#""title": "Loop", "ipf": 0"#)).is_err());
+    assert!(Database::parse(&entry(r#""title": "Loop", "quirks": "cosmac""#)).is_err());
+    assert!(Database::parse(&entry(r#""title": "Loop", "keys": { "G": "fire" }"#)).is_err());
+    assert!(Database::parse(&entry(r#""title": "Loop", "speed": 2"#)).is_err());
+    assert!(Database::parse(r#"{ "pong": { "title": "Pong" } }"#).is_err());
+}
+
+#[test]
+fn default_database() {
+    assert!(!Database::open(None).unwrap().is_empty());
+
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("invalid-database.json");
+    fs::write(&path, "[]").unwrap();
+    assert!(Database::open(Some(&path)).is_err());
+}
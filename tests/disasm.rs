@@ -0,0 +1,59 @@
+//! Mnemonics of the disassembler
+
+use chip8_emulator::disasm::mnemonic;
+
+#[test]
+fn instructions() {
+    let table = [
+        (0x00E0, "CLS"),
+        (0x00EE, "RET"),
+        (0x00C3, "SCD 0x3"),
+        (0x00FB, "SCR"),
+        (0x00FC, "SCL"),
+        (0x00FE, "LOW"),
+        (0x00FF, "HIGH"),
+        (0x0123, "SYS 0x123"),
+        (0x1234, "JP 0x234"),
+        (0x2345, "CALL 0x345"),
+        (0x3A12, "SE VA, 0x12"),
+        (0x4B34, "SNE VB, 0x34"),
+        (0x5120, "SE V1, V2"),
+        (0x6C0F, "LD VC, 0x0f"),
+        (0x7D01, "ADD VD, 0x01"),
+        (0x8120, "LD V1, V2"),
+        (0x8121, "OR V1, V2"),
+        (0x8122, "AND V1, V2"),
+        (0x8123, "XOR V1, V2"),
+        (0x8124, "ADD V1, V2"),
+        (0x8125, "SUB V1, V2"),
+        (0x8126, "SHR V1, V2"),
+        (0x8127, "SUBN V1, V2"),
+        (0x812E, "SHL V1, V2"),
+        (0x9120, "SNE V1, V2"),
+        (0xA123, "LD I, 0x123"),
+        (0xB123, "JP V0, 0x123"),
+        (0xC2FF, "RND V2, 0xff"),
+        (0xD125, "DRW V1, V2, 0x5"),
+        (0xE39E, "SKP V3"),
+        (0xE4A1, "SKNP V4"),
+        (0xF507, "LD V5, DT"),
+        (0xF60A, "LD V6, K"),
+        (0xF715, "LD DT, V7"),
+        (0xF818, "LD ST, V8"),
+        (0xF91E, "ADD I, V9"),
+        (0xFA29, "LD F, VA"),
+        (0xFB33, "LD B, VB"),
+        (0xFC55, "LD [I], VC"),
+        (0xFD65, "LD VD, [I]"),
+    ];
+    for (opcode, expected) in table {
+        assert_eq!(mnemonic(opcode), expected, "{opcode:#06x}");
+    }
+}
+
+#[test]
+fn data() {
+    for opcode in [0x5121, 0x8128, 0x9121, 0xE100, 0xF100] {
+        assert_eq!(mnemonic(opcode), format!("DW {opcode:#06x}"));
+    }
+}
@@ -0,0 +1,87 @@
+//! Persistence of pixels and overlays
+
+use chip8_emulator::effects::{mix, Overlays, Persistence, Phosphor, GRID_BRIGHTNESS};
+use chip8_emulator::palette::Palette;
+
+const ON: [u8; 1] = [0x80];
+const OFF: [u8; 1] = [0x00];
+
+#[test]
+fn cleared_pixels_fade_out() {
+    let mut phosphor = Phosphor::new(8, 1, Persistence::Fade, 4);
+    phosphor.update(&ON);
+    assert_eq!(phosphor.level(0, 0), 1.0);
+
+    let mut levels = vec![];
+    for _ in 0..5 {
+        phosphor.update(&OFF);
+        levels.push(phosphor.level(0, 0));
+    }
+    assert_eq!(levels, [0.75, 0.5, 0.25, 0.0, 0.0]);
+
+    // A pixel drawn again is at full intensity
+    phosphor.update(&ON);
+    assert_eq!(phosphor.level(0, 0), 1.0);
+}
+
+#[test]
+fn blend_the_last_two_frames() {
+    let mut phosphor = Phosphor::new(8, 1, Persistence::Blend, 4);
+    phosphor.update(&ON);
+    assert_eq!(phosphor.level(0, 0), 0.5);
+    phosphor.update(&ON);
+    assert_eq!(phosphor.level(0, 0), 1.0);
+    phosphor.update(&OFF);
+    assert_eq!(phosphor.level(0, 0), 0.5);
+
+    let colors = phosphor.colors(&Palette::classic());
+    assert_eq!(colors[0], [128; 3]);
+    assert_eq!(colors[1], [0; 3]);
+}
+
+#[test]
+fn no_persistence() {
+    let mut phosphor = Phosphor::new(8, 1, Persistence::Off, 4);
+    phosphor.update(&ON);
+    phosphor.update(&OFF);
+    assert_eq!(phosphor.level(0, 0), 0.0);
+    assert_eq!(mix([0; 3], [200, 100, 0], 0.5), [100, 50, 0]);
+}
+
+#[test]
+fn overlays() {
+    let overlays = Overlays {
+        scanlines: true,
+        grid: true,
+    };
+    assert_eq!(overlays.scanline_rows(4), 2..4);
+    assert_eq!(overlays.brightness(0, 0, 4), 1.0);
+    assert_eq!(overlays.brightness(3, 0, 4), GRID_BRIGHTNESS);
+    assert!(overlays.brightness(3, 2, 4) < overlays.brightness(0, 2, 4));
+
+    // Pixels are too small
+    assert!(overlays.scanline_rows(1).is_empty());
+    assert!(!overlays.has_grid(2));
+    assert_eq!(Overlays::default().brightness(3, 3, 4), 1.0);
+}
+
+#[test]
+fn rgba_image_with_overlays() {
+    let mut phosphor = Phosphor::new(8, 1, Persistence::Off, 4);
+    phosphor.update(&ON);
+    let palette = Palette::classic();
+
+    let rgba = phosphor.to_rgba(&palette, 1, &Overlays::default());
+    assert_eq!(rgba.len(), 8 * 4);
+    assert_eq!(rgba[..8], [255, 255, 255, 255, 0, 0, 0, 255]);
+
+    let scanlines = Overlays {
+        scanlines: true,
+        grid: false,
+    };
+    let rgba = phosphor.to_rgba(&palette, 2, &scanlines);
+    assert_eq!(rgba.len(), 16 * 2 * 4);
+    // First row of the pixel is bright, the second one is a scanline
+    assert_eq!(rgba[0], 255);
+    assert_eq!(rgba[16 * 4], 153);
+}
@@ -0,0 +1,185 @@
+//! Packets of the gdb stub, sent by a client on a local connection
+
+use chip8_emulator::emulator::Chip8;
+use chip8_emulator::gdb::GdbStub;
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+/// Instructions run by a poll, like a frame of the frontend
+const BUDGET: usize = 6;
+
+struct Client {
+    stub: GdbStub,
+    chip: Chip8,
+    conn: TcpStream,
+    /// Bytes received and not yet read by a test
+    input: Vec<u8>,
+}
+
+impl Client {
+    fn attach(chip: Chip8) -> Client {
+        let mut stub = GdbStub::bind(0).unwrap();
+        let conn = TcpStream::connect(("127.0.0.1", stub.port().unwrap())).unwrap();
+        conn.set_nonblocking(true).unwrap();
+        let mut chip = chip;
+        stub.poll(&mut chip, BUDGET);
+        Client {
+            stub,
+            chip,
+            conn,
+            input: vec![],
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.conn.write_all(bytes).unwrap();
+    }
+
+    /// Poll the stub until `n` bytes are received
+    fn read(&mut self, n: usize) -> Vec<u8> {
+        let start = Instant::now();
+        while self.input.len() < n {
+            assert!(start.elapsed() < Duration::from_secs(5), "no reply");
+            self.stub.poll(&mut self.chip, BUDGET);
+            let mut buf = [0; 1024];
+            match self.conn.read(&mut buf) {
+                Ok(n) => self.input.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => panic!("{e}"),
+            }
+        }
+        self.input.drain(..n).collect()
+    }
+
+    /// Poll the stub until a packet is received and return its data
+    fn packet(&mut self) -> String {
+        assert_eq!(self.read(1), b"$");
+        let mut data = vec![];
+        loop {
+            match self.read(1)[0] {
+                b'#' => break,
+                b => data.push(b),
+            }
+        }
+        let sum = String::from_utf8(self.read(2)).unwrap();
+        assert_eq!(u8::from_str_radix(&sum, 16).unwrap(), checksum(&data));
+        String::from_utf8(data).unwrap()
+    }
+
+    /// Send `data` in a packet and return the reply
+    fn request(&mut self, data: &str) -> String {
+        let packet = format!("${data}#{:02x}", checksum(data.as_bytes()));
+        self.write(packet.as_bytes());
+        assert_eq!(self.read(1), b"+");
+        self.packet()
+    }
+}
+
+/// Return a machine with `program` at the entry point
+fn with_program(program: &[u16]) -> Chip8 {
+    let mut chip = Chip8::new();
+    for (n, op) in program.iter().enumerate() {
+        let addr = 0x200 + 2 * n;
+        chip.memory_mut()[addr..addr + 2].copy_from_slice(&op.to_be_bytes());
+    }
+    chip
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0_u8, |sum, b| sum.wrapping_add(*b))
+}
+
+#[test]
+fn packets_are_acknowledged() {
+    let mut client = Client::attach(with_program(&[0x1200]));
+    assert_eq!(client.request("?"), "S05");
+
+    // A wrong checksum is rejected and the packet is not handled
+    client.write(b"$?#00");
+    assert_eq!(client.read(1), b"-");
+    assert_eq!(client.request("qC"), "QC1");
+}
+
+#[test]
+fn registers_and_memory() {
+    // LD V1, 0xAB; LD I, 0x345; JP 0x204
+    let mut chip = with_program(&[0x61AB, 0xA345, 0x1204]);
+    chip.step().unwrap();
+    chip.step().unwrap();
+    let mut client = Client::attach(chip);
+
+    let regs = client.request("g");
+    // V0-VF, then I and PC in little endian, SP, DT and ST
+    assert_eq!(&regs[..4], "00ab");
+    assert_eq!(&regs[32..40], "45030402");
+    assert_eq!(regs.len(), 2 * (16 + 2 + 2 + 3));
+
+    assert_eq!(client.request("m200,2"), "61ab");
+    assert_eq!(client.request("M300,3:010203"), "OK");
+    assert_eq!(client.request("m300,3"), "010203");
+    assert_eq!(client.chip.memory()[0x302], 3);
+}
+
+#[test]
+fn out_of_bounds_accesses_are_errors() {
+    let mut client = Client::attach(with_program(&[0x1200]));
+    assert_eq!(client.request("mfff,2"), "E01");
+    assert_eq!(client.request("mffffffffffffffff,2"), "E01");
+    assert_eq!(client.request("Mffffffffffffffff,2:0000"), "E01");
+    assert_eq!(client.request("M300,2:00"), "E01");
+    assert_eq!(client.request("p15"), "E01");
+    assert!(client
+        .request("qXfer:features:read:target.xml:10,ffffffffffffffff")
+        .starts_with('l'));
+}
+
+#[test]
+fn continue_stops_on_breakpoints() {
+    // LD V0, 1; LD V1, 2; JP 0x204
+    let mut client = Client::attach(with_program(&[0x6001, 0x6102, 0x1204]));
+
+    // A breakpoint on the entry point stops right away
+    assert_eq!(client.request("Z0,200,2"), "OK");
+    client.write(b"$c#63");
+    assert_eq!(client.read(1), b"+");
+    assert_eq!(client.packet(), "S05");
+    assert_eq!(client.chip.pc(), 0x200);
+
+    // Resuming doesn't stop again on the same breakpoint
+    assert_eq!(client.request("Z0,204,2"), "OK");
+    client.write(b"$c#63");
+    assert_eq!(client.read(1), b"+");
+    assert_eq!(client.packet(), "S05");
+    assert_eq!(client.chip.pc(), 0x204);
+    assert_eq!(client.chip.vregs()[1], 2);
+
+    // Without breakpoints, an interrupt stops the machine
+    assert_eq!(client.request("z0,200,2"), "OK");
+    assert_eq!(client.request("z0,204,2"), "OK");
+    client.write(b"$c#63");
+    assert_eq!(client.read(1), b"+");
+    client.write(&[0x03]);
+    assert_eq!(client.packet(), "S02");
+}
+
+#[test]
+fn step() {
+    let mut client = Client::attach(with_program(&[0x6001, 0x6102]));
+    assert_eq!(client.request("s"), "S05");
+    assert_eq!(client.chip.pc(), 0x202);
+    assert_eq!(client.chip.vregs()[0], 1);
+}
+
+#[test]
+fn polls_run_a_frame_unless_halted() {
+    // LD V1, 5; LD DT, V1; JP 0x204
+    let mut client = Client::attach(with_program(&[0x6105, 0xF115, 0x1204]));
+    assert!(!client.stub.poll(&mut client.chip, BUDGET));
+    assert_eq!(client.chip.pc(), 0x200);
+
+    assert_eq!(client.request("D"), "OK");
+    assert!(client.stub.poll(&mut client.chip, BUDGET));
+    assert_eq!(client.chip.pc(), 0x204);
+    assert!(client.chip.delay_timer() < 5);
+}
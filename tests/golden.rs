@@ -0,0 +1,159 @@
+//! Golden image regression tests
+//!
+//! Each ROM of `chip8-roms/` is run headlessly and its screen is compared to
+//! a PBM committed in `tests/golden/`. To accept new images after a change
+//! that is expected to modify them, run:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test --no-default-features --test golden
+//! ```
+
+use chip8_emulator::emulator::Chip8;
+use chip8_emulator::headless::{Headless, DEFAULT_IPF};
+use chip8_emulator::image;
+use chip8_emulator::keyscript::KeyScript;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Seed used for ROMs that need random numbers
+const SEED: u64 = 0xC8;
+/// The screen is settled when it doesn't change during this number of frames
+const SETTLE_FRAMES: u64 = 30;
+/// Give up waiting for the screen to settle after this number of frames
+const MAX_FRAMES: u64 = 3000;
+
+/// When to take the screenshot
+enum Until {
+    /// The screen doesn't change anymore
+    Settled,
+    /// After a fixed number of frames, for ROMs that never settle
+    Frames(u64),
+}
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Run the ROM and return its screen as a PBM
+fn run(rom: &str, until: Until) -> String {
+    let mut chip = Chip8::default();
+    chip.load(root().join("chip8-roms").join(rom).to_str().unwrap())
+        .unwrap();
+    chip.set_seed(SEED);
+
+    let mut runner = Headless::new(chip, DEFAULT_IPF, KeyScript::default());
+
+    match until {
+        Until::Settled => {
+            let mut last = runner.chip.get_copy_of_framebuffer();
+            let mut stable = 0;
+            while stable < SETTLE_FRAMES {
+                assert!(
+                    runner.frames() < MAX_FRAMES,
+                    "{rom}: screen is not settled after {MAX_FRAMES} frames"
+                );
+                runner.run_frames(1).unwrap();
+                let fb = runner.chip.get_copy_of_framebuffer();
+                if fb == last {
+                    stable += 1;
+                } else {
+                    stable = 0;
+                    last = fb;
+                }
+            }
+        }
+        Until::Frames(n) => runner.run_frames(n).unwrap(),
+    }
+
+    let (width, height) = runner.chip.resolution();
+    image::to_pbm(runner.chip.get_framebuffer(), width, height)
+}
+
+/// Draw both images side by side. Pixels that differ are shown with `+`
+/// when they are only set in the actual image, `-` when they are only set in
+/// the expected one.
+fn diff(expected: &str, actual: &str) -> Option<String> {
+    let (width, height, expected) = image::from_pbm(expected).expect("invalid golden image");
+    let (w, h, actual) = image::from_pbm(actual).unwrap();
+
+    if (w, h) != (width, height) {
+        return Some(format!("expected a {width}x{height} screen, got {w}x{h}"));
+    }
+
+    let mut count = 0;
+    let mut out = format!("{:width$}   {:width$}   diff\n", "expected", "actual");
+
+    for y in 0..height {
+        let mut lines = [String::new(), String::new(), String::new()];
+        for x in 0..width {
+            let e = image::is_set(&expected, width, x, y);
+            let a = image::is_set(&actual, width, x, y);
+            lines[0].push(if e { '#' } else { '.' });
+            lines[1].push(if a { '#' } else { '.' });
+            lines[2].push(match (e, a) {
+                (true, true) => '#',
+                (false, false) => '.',
+                (false, true) => '+',
+                (true, false) => '-',
+            });
+            if e != a {
+                count += 1;
+            }
+        }
+        out.push_str(&lines.join("   "));
+        out.push('\n');
+    }
+
+    if count == 0 {
+        None
+    } else {
+        Some(format!(
+            "{count} pixels differ (+: only in actual, -: only in expected)\n{out}"
+        ))
+    }
+}
+
+fn check(name: &str, actual: String) {
+    let golden = root()
+        .join("tests")
+        .join("golden")
+        .join(format!("{name}.pbm"));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&golden).unwrap_or_else(|e| {
+        panic!(
+            "Cannot read {}: {e}\nRun with UPDATE_GOLDEN=1 to create it",
+            golden.display()
+        )
+    });
+
+    if let Some(d) = diff(&expected, &actual) {
+        let failed = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.pbm"));
+        fs::write(&failed, &actual).unwrap();
+        panic!(
+            "{name}: screen doesn't match {}\nActual image is in {}\n{d}",
+            golden.display(),
+            failed.display()
+        );
+    }
+}
+
+#[test]
+fn chip8_logo() {
+    check("1-chip8-logo", run("1-chip8-logo.ch8", Until::Settled));
+}
+
+#[test]
+fn corax_plus() {
+    check("3-corax+", run("3-corax+.ch8", Until::Settled));
+}
+
+#[test]
+fn pong() {
+    check("pong", run("pong.ch8", Until::Frames(300)));
+}
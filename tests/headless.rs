@@ -0,0 +1,58 @@
+//! Key scripts and state of the headless runner
+
+use chip8_emulator::emulator::Chip8;
+use chip8_emulator::headless::Headless;
+use chip8_emulator::keyscript::KeyScript;
+use serde_json::{json, Value};
+
+#[test]
+fn key_scripts_press_and_release_keys() {
+    let script = KeyScript::parse("# Press 5 then A\n1 5 down\n3 5 up\n3 a down\n").unwrap();
+    // JP 0x200
+    let chip = Chip8::with_program(&[0x1200]).build();
+    let mut runner = Headless::new(chip, 2, script);
+
+    let mut pressed = vec![];
+    for _ in 0..4 {
+        runner.run_frames(1).unwrap();
+        let keys = runner.chip.keyboard();
+        pressed.push((keys[0x5], keys[0xA]));
+    }
+    assert_eq!(
+        pressed,
+        [(false, false), (true, false), (true, false), (false, true)]
+    );
+
+    assert!(KeyScript::parse("1 g down").is_err());
+    assert!(KeyScript::parse("1 5 pressed").is_err());
+    assert!(KeyScript::parse("x 5 down").is_err());
+}
+
+#[test]
+fn state_json() {
+    let chip = Chip8::with_program(&[
+        0x6012, // LD V0, 0x12
+        0x6103, // LD V1, 3
+        0xA345, // LD I, 0x345
+        0xF115, // LD DT, V1
+        0xF118, // LD ST, V1
+        0x220C, // CALL 0x20C
+        0x120C, // JP 0x20C
+    ])
+    .build();
+    let mut runner = Headless::new(chip, 7, KeyScript::default());
+    runner.run_frames(1).unwrap();
+
+    let state: Value = serde_json::from_str(&runner.state_json()).unwrap();
+    assert_eq!(state["frames"], 1);
+    assert_eq!(state["cycles"], 7);
+    assert_eq!(state["pc"], "0x020c");
+    assert_eq!(state["i"], "0x0345");
+    assert_eq!(state["v"][0], 0x12);
+    assert_eq!(state["v"][1], 3);
+    assert_eq!(state["v"].as_array().unwrap().len(), 16);
+    assert_eq!(state["stack"], json!(["0x020c"]));
+    // The timers are decremented at the end of the frame
+    assert_eq!(state["dt"], 2);
+    assert_eq!(state["st"], 2);
+}
@@ -0,0 +1,63 @@
+//! ASCII and PNG images of the framebuffer
+
+use chip8_emulator::image;
+
+/// 16x2 pixels: a checkerboard on the first row, the last pixel on the second
+const FB: [u8; 4] = [0xAA, 0xAA, 0x00, 0x01];
+
+#[test]
+fn ascii() {
+    assert_eq!(
+        image::to_ascii(&FB, 16, 2),
+        ["#.#.#.#.#.#.#.#.", "...............#"]
+    );
+}
+
+/// Return the CRC-32 of PNG chunks, computed bit by bit
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0_u32, |mut crc, byte| {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 * (crc & 1));
+        }
+        crc
+    })
+}
+
+#[test]
+fn png() {
+    let (fg, bg) = ([0xFF, 0xFF, 0xFF], [0x10, 0x20, 0x30]);
+    let rgb = image::to_rgb(&FB, 16, 2, 2, fg, bg);
+    assert_eq!(rgb.len(), 32 * 4 * 3);
+    let png = image::to_png(&rgb, 32, 4);
+
+    assert_eq!(png[..8], [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+
+    // Walk the chunks, each one is its length, its type, its data and its CRC
+    let mut chunks = vec![];
+    let mut rest = &png[8..];
+    while !rest.is_empty() {
+        let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+        let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+        assert_eq!(crc, crc32(&rest[4..8 + len]), "CRC of {kind:?}");
+        chunks.push((kind, data));
+        rest = &rest[12 + len..];
+    }
+    let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| *kind).collect();
+    assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+
+    // 32x4, 8 bits per channel, RGB, no interlacing
+    let ihdr = chunks[0].1;
+    assert_eq!(ihdr, [0, 0, 0, 32, 0, 0, 0, 4, 8, 2, 0, 0, 0]);
+
+    // A zlib header and a single stored block: each scanline is its filter
+    // type followed by its pixels
+    let idat = chunks[1].1;
+    assert_eq!(idat[..3], [0x78, 0x01, 1]);
+    let scanlines = &idat[7..idat.len() - 4];
+    assert_eq!(scanlines.len(), 4 * (1 + 32 * 3));
+    assert_eq!(scanlines[0], 0);
+    assert_eq!(scanlines[1..7], [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(scanlines[7..10], bg);
+}
@@ -0,0 +1,85 @@
+//! Keymap presets and files
+
+use chip8_emulator::keymap::{Input, Keymap, PRESETS};
+use chip8_emulator::raylib_bindings::gamepad::*;
+use chip8_emulator::raylib_bindings::keys::*;
+
+#[test]
+fn presets_match_the_keypad() {
+    let qwerty = Keymap::qwerty();
+    assert_eq!(qwerty.inputs(0x1), [Input::Key(KEY_ONE)]);
+    assert_eq!(qwerty.inputs(0xC), [Input::Key(KEY_FOUR)]);
+    assert_eq!(qwerty.inputs(0x0), [Input::Key(KEY_X)]);
+    assert_eq!(qwerty.inputs(0xF), [Input::Key(KEY_V)]);
+
+    let azerty = Keymap::azerty();
+    assert_eq!(azerty.inputs(0x4)[0], Input::Key(KEY_A));
+    assert_eq!(azerty.inputs(0x7), [Input::Key(KEY_Q)]);
+    assert_eq!(azerty.inputs(0xA), [Input::Key(KEY_W)]);
+
+    for name in PRESETS {
+        let keymap = Keymap::preset(name).unwrap();
+        let keys = |k| {
+            keymap
+                .inputs(k)
+                .iter()
+                .filter(|i| matches!(i, Input::Key(_)))
+        };
+        assert!((0..16).all(|k| keys(k).count() == 1), "{name}");
+        assert_eq!(
+            keymap.inputs(0x8)[1],
+            Input::Button {
+                pad: 0,
+                button: BUTTON_LEFT_FACE_DOWN
+            }
+        );
+    }
+}
+
+#[test]
+fn file_with_several_host_keys() {
+    let keymap = Keymap::parse("# arrows\n5 w UP\n5 key_kp_8\nf 0\n").unwrap();
+    let keys = [KEY_W, KEY_UP, KEY_KP_8].map(Input::Key);
+    assert_eq!(keymap.inputs(0x5), keys);
+    assert_eq!(keymap.inputs(0xF), [Input::Key(KEY_ZERO)]);
+    assert!(keymap.inputs(0x1).is_empty());
+}
+
+#[test]
+fn file_with_gamepads() {
+    let keymap = Keymap::parse("c pad1:LEFT_FACE_UP pad1:left_y-\nd pad1:axis_left_y+\n").unwrap();
+    assert_eq!(
+        keymap.inputs(0xC),
+        [
+            Input::Button {
+                pad: 1,
+                button: BUTTON_LEFT_FACE_UP
+            },
+            Input::Axis {
+                pad: 1,
+                axis: AXIS_LEFT_Y,
+                positive: false
+            }
+        ]
+    );
+    assert_eq!(
+        keymap.inputs(0xD),
+        [Input::Axis {
+            pad: 1,
+            axis: AXIS_LEFT_Y,
+            positive: true
+        }]
+    );
+
+    assert!(Keymap::parse("1 pad0:left_y\n").is_err());
+    assert!(Keymap::parse("1 pad:a\n").is_err());
+    assert!(Keymap::parse("1 joy0:middle\n").is_err());
+}
+
+#[test]
+fn file_errors() {
+    let err = Keymap::parse("1 q\n10 w\n").unwrap_err();
+    assert!(err.starts_with("2:"), "{err}");
+    assert!(Keymap::parse("1\n").is_err());
+    assert!(Keymap::parse("1 hyper\n").is_err());
+}
@@ -0,0 +1,101 @@
+//! ROM browser and recent ROMs
+
+use chip8_emulator::config::Variant;
+use chip8_emulator::database::Database;
+use chip8_emulator::library::{rom_for, scan, Browser, Recent, MAX_RECENT};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Create an empty directory named `name` for a test
+fn directory(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn variant_of_the_extension() {
+    assert_eq!(Variant::from_path(Path::new("pong.ch8")), Variant::Chip8);
+    assert_eq!(
+        Variant::from_path(Path::new("roms/ant.SC8")),
+        Variant::Schip
+    );
+    assert_eq!(Variant::from_path(Path::new("t8nks.xo8")), Variant::XoChip);
+    assert_eq!(Variant::from_path(Path::new("README")), Variant::Chip8);
+}
+
+#[test]
+fn recent_roms_are_saved() {
+    let dir = directory("recent");
+    let file = dir.join("recent.toml");
+    let (pong, tetris) = (dir.join("pong.ch8"), dir.join("tetris.ch8"));
+
+    let mut recent = Recent::load(&file).unwrap();
+    assert_eq!(recent.roms().count(), 0);
+    recent.add(&pong, 100);
+    recent.add(&tetris, 200);
+    recent.add(&pong, 300);
+    recent.save().unwrap();
+
+    let recent = Recent::load(&file).unwrap();
+    assert_eq!(recent.roms().collect::<Vec<_>>(), [&pong, &tetris]);
+    assert_eq!(recent.played(&pong), Some(300));
+    assert_eq!(recent.played(&tetris), Some(200));
+    assert_eq!(recent.played(&dir.join("ufo.ch8")), None);
+}
+
+#[test]
+fn recent_list_is_bounded() {
+    let mut recent = Recent::new(Path::new("recent.toml"));
+    for i in 0..MAX_RECENT + 5 {
+        recent.add(Path::new(&format!("/roms/{i}.ch8")), i as u64);
+    }
+    assert_eq!(recent.roms().count(), MAX_RECENT);
+    assert_eq!(recent.played(Path::new("/roms/0.ch8")), None);
+}
+
+#[test]
+fn browser_lists_recent_roms_first() {
+    let dir = directory("browser");
+    fs::write(dir.join("b.ch8"), [0x12, 0x00]).unwrap();
+    fs::write(dir.join("a.xo8"), [0x12, 0x00, 0x00, 0x00]).unwrap();
+    fs::write(dir.join("c.ch8"), [0x12, 0x00]).unwrap();
+    fs::write(dir.join("notes.txt"), "not a ROM").unwrap();
+
+    let mut recent = Recent::new(&dir.join("recent.toml"));
+    let roms = scan(&dir, &recent, &Database::default()).unwrap();
+    let names: Vec<String> = roms.iter().map(|r| r.name()).collect();
+    assert_eq!(names, ["a.xo8", "b.ch8", "c.ch8"]);
+    assert_eq!(roms[0].size, 4);
+    assert_eq!(roms[0].variant, Variant::XoChip);
+    assert_eq!(roms[0].played, None);
+
+    recent.add(&dir.join("c.ch8"), 42);
+    let mut browser = Browser::new(&dir, &recent, &Database::default());
+    let names: Vec<String> = browser.entries().iter().map(|r| r.name()).collect();
+    assert_eq!(names, ["c.ch8", "a.xo8", "b.ch8"]);
+    assert_eq!(browser.entries()[0].played, Some(42));
+
+    browser.move_by(-1);
+    assert_eq!(browser.selected_index(), 0);
+    browser.move_by(isize::MAX);
+    assert_eq!(browser.selected().unwrap().name(), "b.ch8");
+    assert_eq!(browser.visible(2), 2..3);
+}
+
+#[test]
+fn dropped_files() {
+    let dir = directory("dropped");
+    let source = dir.join("game.8o");
+    fs::write(&source, ": main jump main").unwrap();
+    assert!(rom_for(&source).is_err());
+
+    fs::write(dir.join("game.sc8"), [0x12, 0x00]).unwrap();
+    assert_eq!(rom_for(&source).unwrap(), dir.join("game.sc8"));
+    assert_eq!(
+        rom_for(Path::new("roms/pong.CH8")).unwrap(),
+        Path::new("roms/pong.CH8")
+    );
+    assert!(rom_for(Path::new("notes.txt")).is_err());
+}
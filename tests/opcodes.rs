@@ -0,0 +1,549 @@
+//! One test per instruction, using programs built with `Chip8::with_program`.
+//!
+//! Delay and sound timers are not decremented by instructions but by
+//! `tick_timers`, at 60 Hz.
+
+use chip8_emulator::emulator::{Chip8, Chip8Error, Quirks, HISTORY_SIZE};
+
+/// Programs are loaded at the entry point
+const ENTRY: usize = 0x200;
+
+#[test]
+fn op_00e0_cls() {
+    // The display is at the end of the memory
+    let mut chip = Chip8::with_program(&[0x00E0])
+        .memory(0xF00, &[0xFF; 256])
+        .build();
+    assert!(chip.get_framebuffer().iter().all(|b| *b == 0xFF));
+    chip.run(1).unwrap();
+    assert!(chip.get_framebuffer().iter().all(|b| *b == 0));
+}
+
+#[test]
+fn op_00ee_ret() {
+    let mut chip = Chip8::with_program(&[0x2204, 0x0000, 0x00EE]).build();
+    chip.run(2).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 2);
+    assert!(chip.stack().is_empty());
+}
+
+#[test]
+fn op_00ee_ret_underflow() {
+    let mut chip = Chip8::with_program(&[0x00EE]).build();
+    assert!(chip.run(1).is_err());
+}
+
+#[test]
+fn op_00ff_high_and_00fe_low() {
+    let mut chip = Chip8::with_program(&[0x00FF, 0xD125, 0x00FE])
+        .vreg(1, 120)
+        .vreg(2, 59)
+        .memory(0xF00, &[0xFF; 256])
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.resolution(), (128, 64));
+    assert_eq!(chip.get_framebuffer().len(), 1024);
+    assert!(chip.get_framebuffer().iter().all(|b| *b == 0));
+
+    // The font "0" fits in the bottom right corner
+    chip.run(1).unwrap();
+    let fb = chip.get_framebuffer();
+    assert_eq!([fb[59 * 16 + 15], fb[63 * 16 + 15]], [0xF0, 0xF0]);
+    let snapshot = chip.snapshot();
+
+    chip.run(1).unwrap();
+    assert_eq!(chip.resolution(), (64, 32));
+    assert!(chip.get_framebuffer().iter().all(|b| *b == 0));
+
+    chip.restore(&snapshot);
+    assert_eq!(chip.resolution(), (128, 64));
+    assert_eq!(chip.get_framebuffer()[63 * 16 + 15], 0xF0);
+
+    // Resets go back to the low resolution
+    chip.reset();
+    assert_eq!(chip.resolution(), (64, 32));
+}
+
+#[test]
+fn op_00cn_scd() {
+    // The pixel at (0, 0) goes down 2 lines
+    let mut chip = Chip8::with_program(&[0x00C2])
+        .memory(0xF00, &[0x80])
+        .build();
+    chip.run(1).unwrap();
+    let fb = chip.get_framebuffer();
+    assert_eq!([fb[0], fb[16]], [0, 0x80]);
+}
+
+#[test]
+fn op_00fb_scr_and_00fc_scl() {
+    let mut chip = Chip8::with_program(&[0x00FB, 0x00FC, 0x00FC])
+        .memory(0xF00, &[0x80, 0x01])
+        .build();
+    chip.run(1).unwrap();
+    let fb = chip.get_framebuffer();
+    assert_eq!([fb[0], fb[1], fb[2]], [0x08, 0x00, 0x10]);
+
+    chip.run(1).unwrap();
+    let fb = chip.get_framebuffer();
+    assert_eq!([fb[0], fb[1]], [0x80, 0x01]);
+
+    // Pixels that leave the display are lost
+    chip.run(1).unwrap();
+    let fb = chip.get_framebuffer();
+    assert_eq!([fb[0], fb[1]], [0x00, 0x10]);
+}
+
+#[test]
+fn op_0nnn_sys_is_ignored() {
+    let mut chip = Chip8::with_program(&[0x0123]).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 2);
+}
+
+#[test]
+fn op_1nnn_jp() {
+    let mut chip = Chip8::with_program(&[0x1345]).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), 0x345);
+}
+
+#[test]
+fn op_2nnn_call() {
+    let mut chip = Chip8::with_program(&[0x2345]).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), 0x345);
+    assert_eq!(chip.stack(), &[ENTRY + 2]);
+}
+
+#[test]
+fn op_3xkk_se() {
+    let mut chip = Chip8::with_program(&[0x3112]).vreg(1, 0x12).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 4);
+
+    let mut chip = Chip8::with_program(&[0x3112]).vreg(1, 0x13).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 2);
+}
+
+#[test]
+fn op_4xkk_sne() {
+    let mut chip = Chip8::with_program(&[0x4112]).vreg(1, 0x13).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 4);
+
+    let mut chip = Chip8::with_program(&[0x4112]).vreg(1, 0x12).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 2);
+}
+
+#[test]
+fn op_5xy0_se() {
+    let mut chip = Chip8::with_program(&[0x5120]).vreg(1, 7).vreg(2, 7).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 4);
+
+    let mut chip = Chip8::with_program(&[0x5120]).vreg(1, 7).vreg(2, 8).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 2);
+}
+
+#[test]
+fn op_6xkk_ld() {
+    let mut chip = Chip8::with_program(&[0x6A42]).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(0xA), 0x42);
+}
+
+#[test]
+fn op_7xkk_add_wraps_without_carry() {
+    let mut chip = Chip8::with_program(&[0x7102]).vreg(1, 0xFF).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 0x01);
+    assert_eq!(chip.vreg(0xF), 0);
+}
+
+#[test]
+fn op_8xy0_ld() {
+    let mut chip = Chip8::with_program(&[0x8120]).vreg(2, 0x33).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 0x33);
+}
+
+#[test]
+fn op_8xy1_or() {
+    let mut chip = Chip8::with_program(&[0x8121])
+        .vreg(1, 0xF0)
+        .vreg(2, 0x0F)
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 0xFF);
+}
+
+#[test]
+fn op_8xy2_and() {
+    let mut chip = Chip8::with_program(&[0x8122])
+        .vreg(1, 0xF3)
+        .vreg(2, 0x3F)
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 0x33);
+}
+
+#[test]
+fn op_8xy3_xor() {
+    let mut chip = Chip8::with_program(&[0x8123])
+        .vreg(1, 0xFF)
+        .vreg(2, 0x0F)
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 0xF0);
+}
+
+#[test]
+fn op_8xy4_add_sets_carry() {
+    let mut chip = Chip8::with_program(&[0x6012, 0x7001, 0x8014, 0x8014])
+        .vreg(1, 0xFF)
+        .build();
+    chip.run(3).unwrap();
+    assert_eq!(chip.vreg(0), 0x12);
+    assert_eq!(chip.vreg(0xF), 1);
+
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(0), 0x11);
+    assert_eq!(chip.vreg(0xF), 1);
+
+    let mut chip = Chip8::with_program(&[0x8124]).vreg(1, 1).vreg(2, 2).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 3);
+    assert_eq!(chip.vreg(0xF), 0);
+}
+
+#[test]
+fn op_8xy5_sub() {
+    let mut chip = Chip8::with_program(&[0x8125]).vreg(1, 5).vreg(2, 3).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 2);
+    assert_eq!(chip.vreg(0xF), 1);
+
+    let mut chip = Chip8::with_program(&[0x8125]).vreg(1, 3).vreg(2, 5).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 0xFE);
+    assert_eq!(chip.vreg(0xF), 0);
+}
+
+#[test]
+fn op_8xy6_shr() {
+    let mut chip = Chip8::with_program(&[0x8126]).vreg(1, 0x05).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 0x02);
+    assert_eq!(chip.vreg(0xF), 1);
+}
+
+#[test]
+fn op_8xy7_subn() {
+    let mut chip = Chip8::with_program(&[0x8127]).vreg(1, 3).vreg(2, 5).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 2);
+    assert_eq!(chip.vreg(0xF), 1);
+
+    let mut chip = Chip8::with_program(&[0x8127]).vreg(1, 5).vreg(2, 3).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 0xFE);
+    assert_eq!(chip.vreg(0xF), 0);
+}
+
+#[test]
+fn op_8xye_shl() {
+    let mut chip = Chip8::with_program(&[0x812E]).vreg(1, 0x81).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 0x02);
+    assert_eq!(chip.vreg(0xF), 1);
+}
+
+#[test]
+fn op_9xy0_sne() {
+    let mut chip = Chip8::with_program(&[0x9120]).vreg(1, 7).vreg(2, 8).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 4);
+
+    let mut chip = Chip8::with_program(&[0x9120]).vreg(1, 7).vreg(2, 7).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 2);
+}
+
+#[test]
+fn op_annn_ld_i() {
+    let mut chip = Chip8::with_program(&[0xA123]).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.i(), 0x123);
+}
+
+#[test]
+fn op_bnnn_jp_v0() {
+    let mut chip = Chip8::with_program(&[0xB300]).vreg(0, 0x10).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), 0x310);
+}
+
+#[test]
+fn op_cxkk_rnd_is_masked() {
+    let mut chip = Chip8::with_program(&[0xC10F, 0xC200]).seed(1).build();
+    chip.run(2).unwrap();
+    assert_eq!(chip.vreg(1) & 0xF0, 0);
+    assert_eq!(chip.vreg(2), 0);
+}
+
+#[test]
+fn op_dxyn_drw() {
+    // Draw the font "0" (F0 90 90 90 F0) at (0, 0) on a cleared screen
+    let mut chip = Chip8::with_program(&[0x00E0, 0xD015, 0xD015]).build();
+    chip.run(2).unwrap();
+    let fb = chip.get_framebuffer();
+    assert_eq!(
+        [fb[0], fb[8], fb[16], fb[24], fb[32]],
+        [0xF0, 0x90, 0x90, 0x90, 0xF0]
+    );
+    assert_eq!(chip.vreg(0xF), 0);
+
+    // Drawing it again erases it and reports the collision
+    chip.run(1).unwrap();
+    assert!(chip.get_framebuffer().iter().all(|b| *b == 0));
+    assert_eq!(chip.vreg(0xF), 1);
+}
+
+#[test]
+fn op_dxyn_drw_unaligned() {
+    let mut chip = Chip8::with_program(&[0x00E0, 0xD121])
+        .vreg(1, 12)
+        .vreg(2, 1)
+        .memory(0x300, &[0xFF])
+        .i(0x300)
+        .build();
+    chip.run(2).unwrap();
+    let fb = chip.get_framebuffer();
+    assert_eq!([fb[9], fb[10]], [0x0F, 0xF0]);
+}
+
+#[test]
+fn op_dxy0_drw_16x16() {
+    let mut sprite = [0; 32];
+    sprite[..4].copy_from_slice(&[0xFF, 0xFF, 0x80, 0x01]);
+    let mut chip = Chip8::with_program(&[0x00FF, 0xD120, 0xD120])
+        .vreg(1, 4)
+        .vreg(2, 2)
+        .memory(0x300, &sprite)
+        .i(0x300)
+        .build();
+    chip.run(2).unwrap();
+    let fb = chip.get_framebuffer();
+    // Rows are 16 bytes in high resolution
+    assert_eq!(&fb[32..35], [0x0F, 0xFF, 0xF0]);
+    assert_eq!(&fb[48..51], [0x08, 0x00, 0x10]);
+    assert_eq!(chip.vreg(0xF), 0);
+
+    chip.run(1).unwrap();
+    assert!(chip.get_framebuffer().iter().all(|b| *b == 0));
+    assert_eq!(chip.vreg(0xF), 1);
+}
+
+#[test]
+fn op_ex9e_skp() {
+    let mut chip = Chip8::with_program(&[0xE19E]).vreg(1, 0xA).key(0xA).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 4);
+
+    let mut chip = Chip8::with_program(&[0xE19E]).vreg(1, 0xA).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 2);
+}
+
+#[test]
+fn op_exa1_sknp() {
+    let mut chip = Chip8::with_program(&[0xE1A1]).vreg(1, 0xA).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 4);
+
+    let mut chip = Chip8::with_program(&[0xE1A1]).vreg(1, 0xA).key(0xA).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 2);
+}
+
+#[test]
+fn op_fx07_ld_vx_dt() {
+    let mut chip = Chip8::with_program(&[0xF107]).delay_timer(10).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 10);
+}
+
+#[test]
+fn op_fx0a_ld_vx_k_waits_for_a_key() {
+    let mut chip = Chip8::with_program(&[0xF10A]).build();
+    chip.run(3).unwrap();
+    assert_eq!(chip.pc(), ENTRY);
+
+    chip.set_key(0xB, true);
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), ENTRY + 2);
+    assert_eq!(chip.vreg(1), 0xB);
+}
+
+#[test]
+fn op_fx15_ld_dt_vx() {
+    let mut chip = Chip8::with_program(&[0xF115, 0x0000]).vreg(1, 20).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.delay_timer(), 20);
+    chip.run(1).unwrap();
+    assert_eq!(chip.delay_timer(), 20);
+    chip.tick_timers();
+    assert_eq!(chip.delay_timer(), 19);
+}
+
+#[test]
+fn op_fx18_ld_st_vx() {
+    let mut chip = Chip8::with_program(&[0xF118]).vreg(1, 20).build();
+    chip.run_frame(1).unwrap();
+    assert_eq!(chip.sound_timer(), 19);
+}
+
+#[test]
+fn op_fx1e_add_i_vx() {
+    let mut chip = Chip8::with_program(&[0xF11E])
+        .vreg(1, 0x10)
+        .i(0x300)
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.i(), 0x310);
+}
+
+#[test]
+fn op_fx29_ld_f_vx() {
+    let mut chip = Chip8::with_program(&[0xF129]).vreg(1, 0xA).build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.i(), 50);
+    assert_eq!(&chip.memory()[50..55], &[0xF0, 0x90, 0xF0, 0x90, 0x90]);
+
+    let mut chip = Chip8::with_program(&[0xF129]).vreg(1, 0x10).build();
+    assert!(chip.run(1).is_err());
+}
+
+#[test]
+fn op_fx33_ld_b_vx() {
+    let mut chip = Chip8::with_program(&[0xF133]).vreg(1, 254).i(0x300).build();
+    chip.run(1).unwrap();
+    assert_eq!(&chip.memory()[0x300..0x303], &[2, 5, 4]);
+}
+
+#[test]
+fn op_fx55_ld_i_vx() {
+    let mut chip = Chip8::with_program(&[0xF255])
+        .vreg(0, 1)
+        .vreg(1, 2)
+        .vreg(2, 3)
+        .vreg(3, 4)
+        .i(0x300)
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(&chip.memory()[0x300..0x304], &[1, 2, 3, 0]);
+    assert_eq!(chip.i(), 0x300);
+}
+
+#[test]
+fn op_fx65_ld_vx_i() {
+    let mut chip = Chip8::with_program(&[0xF265])
+        .memory(0x300, &[1, 2, 3, 4])
+        .i(0x300)
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(&chip.vregs()[0..4], &[1, 2, 3, 0]);
+    assert_eq!(chip.i(), 0x300);
+}
+
+#[test]
+fn unknown_opcode() {
+    for opcode in [0x5121, 0x8128, 0x9121, 0xE1FF, 0xF1FF] {
+        let mut chip = Chip8::with_program(&[opcode]).build();
+        assert!(chip.run(1).is_err(), "{opcode:#06x} should be unknown");
+    }
+}
+
+#[test]
+fn quirk_vf_reset() {
+    let mut chip = Chip8::with_program(&[0x8121])
+        .vreg(0xF, 1)
+        .quirks(Quirks::VIP)
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(0xF), 0);
+}
+
+#[test]
+fn quirk_shift_vy() {
+    let mut chip = Chip8::with_program(&[0x812E])
+        .vreg(1, 0x01)
+        .vreg(2, 0x81)
+        .quirks(Quirks::VIP)
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 0x02);
+    assert_eq!(chip.vreg(0xF), 1);
+}
+
+#[test]
+fn quirk_jump_vx() {
+    let mut chip = Chip8::with_program(&[0xB300])
+        .vreg(0, 0x10)
+        .vreg(3, 0x20)
+        .quirks(Quirks::SCHIP)
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.pc(), 0x320);
+}
+
+#[test]
+fn quirk_load_store_increment_i() {
+    let mut chip = Chip8::with_program(&[0xF255, 0xF265])
+        .i(0x300)
+        .quirks(Quirks::VIP)
+        .build();
+    chip.run(1).unwrap();
+    assert_eq!(chip.i(), 0x303);
+    chip.run(1).unwrap();
+    assert_eq!(chip.i(), 0x306);
+}
+
+#[test]
+fn quirk_presets() {
+    for name in Quirks::PRESETS {
+        assert!(name.parse::<Quirks>().is_ok(), "{name}");
+    }
+    assert!("cosmac".parse::<Quirks>().is_err());
+}
+
+#[test]
+fn history_ends_with_the_failing_instruction() {
+    // LD V0, 1; JP 0x204; then an unknown opcode
+    let mut chip = Chip8::with_program(&[0x6001, 0x1204, 0xFFFF]).build();
+    assert!(chip.run(3).is_err());
+
+    let history: Vec<(usize, u16)> = chip.history().iter().map(|e| (e.pc, e.opcode)).collect();
+    assert_eq!(
+        history,
+        [(ENTRY, 0x6001), (ENTRY + 2, 0x1204), (ENTRY + 4, 0xFFFF)]
+    );
+
+    let mut chip = Chip8::with_program(&[0x1200]).build();
+    chip.run(HISTORY_SIZE + 5).unwrap();
+    assert_eq!(chip.history().len(), HISTORY_SIZE);
+}
+
+#[test]
+fn missing_rom_is_an_error() {
+    let mut chip = Chip8::default();
+    assert_eq!(
+        chip.load("chip8-roms/missing.ch8"),
+        Err(Chip8Error::Io(std::io::ErrorKind::NotFound))
+    );
+    assert!(chip.load("chip8-roms/pong.ch8").is_ok());
+}
@@ -0,0 +1,72 @@
+//! Soft and hard resets of the machine
+
+use chip8_emulator::emulator::Chip8;
+use chip8_emulator::headless::Headless;
+use chip8_emulator::keyscript::KeyScript;
+
+const ENTRY: usize = 0x200;
+
+/// Draw the digit 5, call a subroutine that stores V0 at 0x300
+const PROGRAM: [u16; 6] = [0x6005, 0xF029, 0xD005, 0x2208, 0xA300, 0xF055];
+
+#[test]
+fn reset_keeps_the_memory() {
+    let mut chip = Chip8::with_program(&PROGRAM).build();
+    chip.run(PROGRAM.len()).unwrap();
+    assert_eq!(chip.stack().len(), 1);
+    assert_eq!(chip.memory()[0x300], 5);
+    assert!(chip.get_framebuffer().iter().any(|b| *b != 0));
+
+    chip.reset();
+    assert_eq!(chip.pc(), ENTRY);
+    assert!(chip.vregs().iter().all(|v| *v == 0));
+    assert_eq!(chip.i(), 0);
+    assert!(chip.stack().is_empty());
+    assert!(chip.get_framebuffer().iter().all(|b| *b == 0));
+    assert!(chip.history().is_empty());
+    assert_eq!(chip.memory()[0x300], 5);
+}
+
+#[test]
+fn hard_reset_reloads_the_rom() {
+    let mut chip = Chip8::with_program(&PROGRAM).build();
+    let rom = chip.memory()[ENTRY..ENTRY + 2 * PROGRAM.len()].to_vec();
+    let fonts = chip.memory()[..80].to_vec();
+    chip.run(PROGRAM.len()).unwrap();
+    // The program overwrites itself
+    chip.memory_mut()[ENTRY] = 0xFF;
+
+    chip.hard_reset();
+    assert_eq!(chip.pc(), ENTRY);
+    assert_eq!(chip.memory()[0x300], 0);
+    assert_eq!(chip.memory()[ENTRY..ENTRY + rom.len()], rom);
+    assert_eq!(chip.memory()[..80], fonts);
+    assert_eq!(chip.rom(), rom);
+}
+
+#[test]
+fn hard_reset_restarts_the_random_generator() {
+    // RND V0, 0xFF
+    let mut chip = Chip8::with_program(&[0xC0FF, 0xC0FF]).seed(7).build();
+    chip.run(2).unwrap();
+    let second = chip.vreg(0);
+
+    chip.hard_reset();
+    chip.run(2).unwrap();
+    assert_eq!(chip.vreg(0), second);
+}
+
+#[test]
+fn key_scripts_reset() {
+    // ADD V0, 1; JP 0x200
+    let run = |script: &str| {
+        let chip = Chip8::with_program(&[0x7001, 0x1200]).build();
+        let mut runner = Headless::new(chip, 2, KeyScript::parse(script).unwrap());
+        runner.run_frames(3).unwrap();
+        runner.chip.vreg(0)
+    };
+    assert_eq!(run(""), 3);
+    assert_eq!(run("1 reset"), 2);
+    assert_eq!(run("2 hard-reset"), 1);
+    assert!(KeyScript::parse("1 reboot").is_err());
+}
@@ -0,0 +1,40 @@
+//! Dates and timestamped file names
+
+use chip8_emulator::timestamp::{date_time, file_name, now};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn dates() {
+    assert_eq!(date_time(0), "1970-01-01 00:00");
+    // 2000 is a leap year because it is divisible by 400
+    assert_eq!(date_time(951782400), "2000-02-29 00:00");
+    assert_eq!(date_time(951782400 + 86400), "2000-03-01 00:00");
+    assert_eq!(date_time(1704067199), "2023-12-31 23:59");
+    assert_eq!(date_time(1704067200), "2024-01-01 00:00");
+    assert_eq!(date_time(1714571100), "2024-05-01 13:45");
+}
+
+#[test]
+fn file_names_are_numbered_when_taken() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("timestamp");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    // Names change every second, try again if the second is over
+    loop {
+        let start = now();
+        let first = file_name(&dir, "chip8", "png");
+        fs::write(&first, []).unwrap();
+        let second = file_name(&dir, "chip8", "png");
+        if now() != start {
+            continue;
+        }
+
+        let stem = first.file_stem().unwrap().to_str().unwrap();
+        assert!(stem.starts_with("chip8-"));
+        assert_eq!(first.extension().unwrap(), "png");
+        assert_eq!(second, dir.join(format!("{stem}-1.png")));
+        break;
+    }
+}
@@ -0,0 +1,125 @@
+//! Traces written by the tracer, read back and compared
+
+use chip8_emulator::emulator::Chip8;
+use chip8_emulator::trace::{first_divergence, read_trace, Record, Tracer};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Trace three instructions of a program in `name`
+fn write_trace(name: &str) -> PathBuf {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    // LD V0, 0x12; ADD V1, 0xFF; LD I, 0x345
+    let mut chip = Chip8::new();
+    chip.memory_mut()[0x200..0x206].copy_from_slice(&[0x60, 0x12, 0x71, 0xFF, 0xA3, 0x45]);
+    chip.set_tracer(Some(Tracer::create(&path).unwrap()));
+    for _ in 0..3 {
+        chip.step().unwrap();
+    }
+    // Flush the trace
+    chip.set_tracer(None);
+    path
+}
+
+fn record(fields: &[(&str, u64)]) -> Record {
+    fields.iter().map(|(n, v)| (n.to_string(), *v)).collect()
+}
+
+fn check_round_trip(records: &[Record]) {
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0]["cycle"], 0);
+    assert_eq!(records[0]["pc"], 0x200);
+    assert_eq!(records[0]["opcode"], 0x6012);
+    assert_eq!(records[1]["v0"], 0x12);
+    assert_eq!(records[2]["v1"], 0xFF);
+    assert_eq!(records[2]["pc"], 0x204);
+    assert_eq!(records[2]["i"], 0);
+    assert_eq!(records[2]["dt"], 0);
+    // The mnemonic is not a number
+    assert!(!records[0].contains_key("mnemonic"));
+}
+
+#[test]
+fn jsonl_round_trip() {
+    let path = write_trace("trace.jsonl");
+    assert!(fs::read_to_string(&path).unwrap().starts_with('{'));
+    check_round_trip(&read_trace(&path).unwrap());
+}
+
+#[test]
+fn csv_round_trip() {
+    let path = write_trace("trace.csv");
+    let content = fs::read_to_string(&path).unwrap();
+    // Mnemonics have commas, they are quoted
+    assert!(content.contains(r#""LD V0, 0x12""#));
+    let records = read_trace(&path).unwrap();
+    check_round_trip(&records);
+    assert_eq!(records[0].len(), 3 + 16 + 3);
+}
+
+#[test]
+fn reference_traces() {
+    // Another emulator can write values in decimal or hexadecimal, and V
+    // registers as fields
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("reference.jsonl");
+    fs::write(
+        &path,
+        "{\"PC\": 512, \"V0\": \"0x12\", \"i\": \"0X345\"}\n\n{\"pc\": \"0x202\", \"v\": [1, 2]}\n",
+    )
+    .unwrap();
+    let records = read_trace(&path).unwrap();
+    assert_eq!(
+        records,
+        [
+            record(&[("pc", 0x200), ("v0", 0x12), ("i", 0x345)]),
+            record(&[("pc", 0x202), ("v0", 1), ("v1", 2)]),
+        ]
+    );
+
+    fs::write(&path, "{\"pc\": 512\n").unwrap();
+    assert!(read_trace(&path).is_err());
+}
+
+#[test]
+fn equal_traces_dont_diverge() {
+    let ours = [record(&[("pc", 0x200), ("v0", 1)])];
+    // Fields missing from a record are not compared
+    let reference = [record(&[("pc", 0x200)])];
+    assert!(first_divergence(&ours, &ours, &[]).is_none());
+    assert!(first_divergence(&ours, &reference, &[]).is_none());
+}
+
+#[test]
+fn traces_diverge_on_a_register() {
+    let ours = [
+        record(&[("pc", 0x200), ("v0", 1), ("dt", 3)]),
+        record(&[("pc", 0x202), ("v0", 2), ("dt", 3)]),
+    ];
+    let reference = [
+        record(&[("pc", 0x200), ("v0", 1), ("dt", 3)]),
+        record(&[("pc", 0x202), ("v0", 3), ("dt", 2)]),
+    ];
+
+    let divergence = first_divergence(&ours, &reference, &[]).unwrap();
+    assert_eq!(divergence.index, 1);
+    assert_eq!(divergence.fields, ["dt", "v0"]);
+    assert_eq!(divergence.ours.as_ref(), Some(&ours[1]));
+    assert_eq!(divergence.reference.as_ref(), Some(&reference[1]));
+
+    let divergence = first_divergence(&ours, &reference, &["dt".to_string()]).unwrap();
+    assert_eq!(divergence.fields, ["v0"]);
+}
+
+#[test]
+fn shorter_trace_diverges() {
+    let ours = [record(&[("pc", 0x200)]), record(&[("pc", 0x202)])];
+
+    let divergence = first_divergence(&ours, &ours[..1], &[]).unwrap();
+    assert_eq!(divergence.index, 1);
+    assert!(divergence.fields.is_empty());
+    assert_eq!(divergence.ours.as_ref(), Some(&ours[1]));
+    assert!(divergence.reference.is_none());
+
+    let divergence = first_divergence(&[], &ours, &[]).unwrap();
+    assert_eq!(divergence.index, 0);
+    assert!(divergence.ours.is_none());
+}
@@ -0,0 +1,93 @@
+//! Videos recorded by a headless run
+
+use chip8_emulator::emulator::Chip8;
+use chip8_emulator::headless::{Headless, DEFAULT_IPF};
+use chip8_emulator::keyscript::KeyScript;
+use chip8_emulator::video::VideoRecorder;
+use std::fs;
+use std::path::Path;
+
+const SCALE: usize = 2;
+
+/// Run `program` for `frames` frames and return the content of the video
+fn record(file: &str, program: &[u16], frames: u64) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(file);
+
+    let chip = Chip8::with_program(program).build();
+    let (width, height) = chip.resolution();
+    let mut runner = Headless::new(chip, DEFAULT_IPF, KeyScript::default());
+    runner.set_video(Some(
+        VideoRecorder::create(&path, width, height, SCALE, [255; 3], [0; 3]).unwrap(),
+    ));
+    runner.run_frames(frames).unwrap();
+    runner.set_video(None);
+
+    fs::read(&path).unwrap()
+}
+
+/// Return the delay of each frame of a GIF
+fn gif_delays(gif: &[u8]) -> Vec<u16> {
+    assert_eq!(&gif[0..6], b"GIF89a");
+    let table = 3 << ((gif[10] & 0x07) + 1);
+    let mut p = 13 + table;
+    let mut delays = vec![];
+
+    // Skip sub-blocks until the terminator
+    let skip_blocks = |mut p: usize| {
+        while gif[p] != 0 {
+            p += gif[p] as usize + 1;
+        }
+        p + 1
+    };
+
+    loop {
+        match gif[p] {
+            0x3B => return delays,
+            0x21 => {
+                if gif[p + 1] == 0xF9 {
+                    delays.push(u16::from_le_bytes([gif[p + 4], gif[p + 5]]));
+                }
+                p = skip_blocks(p + 2);
+            }
+            0x2C => p = skip_blocks(p + 11),
+            b => panic!("unexpected block {b:#04x} at {p}"),
+        }
+    }
+}
+
+#[test]
+fn gif_merges_identical_frames() {
+    // CLS; LD V0, 0; LD F, V0; DRW V0, V0, 5; JP 0x208
+    let gif = record("video.gif", &[0x00E0, 0x6000, 0xF029, 0xD005, 0x1208], 120);
+
+    assert_eq!(u16::from_le_bytes([gif[6], gif[7]]), 64 * SCALE as u16);
+    assert_eq!(u16::from_le_bytes([gif[8], gif[9]]), 32 * SCALE as u16);
+
+    // The digit is drawn during the first frame and never changes
+    assert_eq!(gif_delays(&gif), [200], "one image of two seconds");
+}
+
+#[test]
+fn gif_delays_add_up_to_the_duration() {
+    // Draw the digit again and again, so it blinks each frame
+    let gif = record("blink.gif", &[0x00E0, 0xD005, 0x1202], 90);
+
+    let delays = gif_delays(&gif);
+    assert!(delays.len() > 1);
+    assert_eq!(delays.iter().map(|d| *d as u32).sum::<u32>(), 150);
+}
+
+#[test]
+fn y4m_has_one_frame_per_60th_of_second() {
+    let y4m = record("video.y4m", &[0x00E0, 0x1202], 30);
+
+    let header = format!(
+        "YUV4MPEG2 W{} H{} F60:1 Ip A1:1 C444\n",
+        64 * SCALE,
+        32 * SCALE
+    );
+    assert!(y4m.starts_with(header.as_bytes()));
+
+    let frame_size = b"FRAME\n".len() + 3 * 64 * 32 * SCALE * SCALE;
+    assert_eq!(y4m.len(), header.len() + 30 * frame_size);
+}
@@ -0,0 +1,35 @@
+//! Letterboxing of the display in the window
+
+use chip8_emulator::viewport::Viewport;
+
+#[test]
+fn letterbox_keeps_square_pixels() {
+    // Too wide, borders on the sides
+    let viewport = Viewport::letterbox((1000, 320), (64, 32), false);
+    assert_eq!(viewport.pixel_size, 10.0);
+    assert_eq!((viewport.x, viewport.y), (180.0, 0.0));
+    assert_eq!(viewport.size(), (640.0, 320.0));
+
+    // Too high, borders at the top and the bottom
+    let viewport = Viewport::letterbox((640, 500), (64, 32), false);
+    assert_eq!((viewport.x, viewport.y), (0.0, 90.0));
+    assert_eq!(viewport.pixel(1, 1), (10.0, 100.0));
+
+    // The high resolution of SCHIP has the same aspect ratio
+    let viewport = Viewport::letterbox((1280, 640), (128, 64), false);
+    assert_eq!(viewport.pixel_size, 10.0);
+}
+
+#[test]
+fn integer_scale() {
+    let viewport = Viewport::letterbox((700, 350), (64, 32), false);
+    assert!(viewport.pixel_size > 10.9);
+
+    let viewport = Viewport::letterbox((700, 350), (64, 32), true);
+    assert_eq!(viewport.pixel_size, 10.0);
+    assert_eq!((viewport.x, viewport.y), (30.0, 15.0));
+
+    // Pixels are never smaller than 1
+    let viewport = Viewport::letterbox((32, 16), (64, 32), true);
+    assert_eq!(viewport.pixel_size, 1.0);
+}
@@ -0,0 +1,61 @@
+//! Hot reload of ROMs that change on the disk
+
+use chip8_emulator::emulator::Chip8;
+use chip8_emulator::watch::RomWatcher;
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Write `rom` at `path` with a modification time `secs` after the epoch,
+/// so changes are seen whatever the precision of the file system
+fn write(path: &Path, rom: &[u8], secs: u64) {
+    fs::write(path, rom).unwrap();
+    let file = File::options().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+        .unwrap();
+}
+
+#[test]
+fn changes_are_detected() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("watched.ch8");
+    write(&path, &[0x12, 0x00], 1);
+    let mut watcher = RomWatcher::new(&path, &[0x12, 0x00]);
+    assert_eq!(watcher.check().unwrap(), None);
+
+    // Saved again without change
+    write(&path, &[0x12, 0x00], 2);
+    assert_eq!(watcher.check().unwrap(), None);
+
+    // Being written
+    write(&path, &[], 3);
+    assert_eq!(watcher.check().unwrap(), None);
+
+    write(&path, &[0x60, 0x01, 0x12, 0x02], 4);
+    assert_eq!(watcher.check().unwrap(), Some(vec![0x60, 0x01, 0x12, 0x02]));
+    assert_eq!(watcher.check().unwrap(), None);
+}
+
+#[test]
+fn snapshot_is_restored_with_the_new_rom() {
+    // LD V0, 7; LD I, 0x300; LD [I], V0; JP 0x206
+    let mut chip = Chip8::with_program(&[0x6007, 0xA300, 0xF055, 0x1206]).build();
+    chip.run(3).unwrap();
+    let snapshot = chip.snapshot();
+    chip.run(10).unwrap();
+
+    // The new version loops on LD V1, 1; JP 0x206
+    let new: Vec<u8> = [0x6007, 0xA300, 0xF055, 0x6101, 0x1206]
+        .iter()
+        .flat_map(|op| u16::to_be_bytes(*op))
+        .collect();
+    chip.load_bytes(&new).unwrap();
+    chip.restore(&snapshot);
+    chip.reload_rom();
+
+    assert_eq!(chip.pc(), 0x206);
+    assert_eq!(chip.vreg(0), 7);
+    assert_eq!(chip.memory()[0x300], 7);
+    assert_eq!(chip.memory()[0x200..0x20A], new);
+    chip.run(1).unwrap();
+    assert_eq!(chip.vreg(1), 1);
+}